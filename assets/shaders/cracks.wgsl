@@ -0,0 +1,11 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+// Crack stage in `x`
+@group(2) @binding(0) var<uniform> stage: vec4<u32>;
+@group(2) @binding(1) var cracks_texture: texture_2d_array<f32>;
+@group(2) @binding(2) var cracks_sampler: sampler;
+
+@fragment
+fn fragment(mesh: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(cracks_texture, cracks_sampler, mesh.uv, stage.x);
+}
@@ -18,6 +18,18 @@ pub mod loading;
 pub use loading::*;
 pub mod generation;
 pub use generation::*;
+pub mod raycast;
+pub use raycast::*;
+pub mod ambient_occlusion;
+pub use ambient_occlusion::*;
+pub mod light;
+pub use light::*;
+pub mod shading;
+pub use shading::*;
+pub mod culling;
+pub use culling::*;
+pub mod lod;
+pub use lod::*;
 
 use serde::{Deserialize, Serialize};
 
@@ -28,7 +40,12 @@ impl Plugin for BlocAndChunkPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(CracksPlugin)
             .add_plugins(LoadingPlugin)
-            .add_systems(Update, apply_next_material)
+            .add_plugins(LightPlugin)
+            .add_plugins(ShadingPlugin)
+            .add_plugins(CullingPlugin)
+            .add_plugins(LodPlugin)
+            .init_resource::<BlocAssets>()
+            .add_systems(Startup, setup_bloc_assets)
             .add_systems(Update, link_chunks::<DefaultGenerator>);
     }
 }
@@ -41,6 +58,90 @@ pub enum BlocType {
     Sand,
     SnowyDirt,
     Air,
+    Glowstone,
+    Glass,
+    Leaves,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlocRendering {
+    Invisible,
+    Opaque,
+    Cutout, // leaves
+    Translucent // glass
+}
+impl BlocRendering {
+    pub fn alpha_mode(&self) -> AlphaMode {
+        match self {
+            BlocRendering::Invisible | BlocRendering::Opaque => AlphaMode::Opaque,
+            BlocRendering::Cutout => AlphaMode::Mask(0.5),
+            BlocRendering::Translucent => AlphaMode::Blend
+        }
+    }
+}
+
+impl BlocType {
+    pub fn list() -> [BlocType; 9] {
+        [
+            BlocType::Dirt,
+            BlocType::Grass,
+            BlocType::Stone,
+            BlocType::Sand,
+            BlocType::SnowyDirt,
+            BlocType::Air,
+            BlocType::Glowstone,
+            BlocType::Glass,
+            BlocType::Leaves
+        ]
+    }
+    /// Time (in seconds) to break the bloc by hand in survival
+    pub fn break_time(&self) -> f32 {
+        match self {
+            BlocType::Dirt => 0.75,
+            BlocType::Grass => 0.9,
+            BlocType::Stone => 2.0,
+            BlocType::Sand => 0.75,
+            BlocType::SnowyDirt => 0.75,
+            BlocType::Air => 0.0,
+            BlocType::Glowstone => 0.45,
+            BlocType::Glass => 0.45,
+            BlocType::Leaves => 0.3
+        }
+    }
+    pub fn light_emission(&self) -> u8 {
+        match self {
+            BlocType::Glowstone => MAX_LIGHT,
+            _ => 0
+        }
+    }
+    pub fn rendering(&self) -> BlocRendering {
+        match self {
+            BlocType::Air => BlocRendering::Invisible,
+            BlocType::Glass => BlocRendering::Translucent,
+            BlocType::Leaves => BlocRendering::Cutout,
+            _ => BlocRendering::Opaque
+        }
+    }
+    pub fn is_opaque(&self) -> bool {
+        self.rendering() == BlocRendering::Opaque
+    }
+    // Two translucent blocs of the same type are drawn as one, without the faces between them
+    pub fn hides_face_of(&self, bloc: BlocType) -> bool {
+        self.is_opaque() || (*self == bloc && self.rendering() == BlocRendering::Translucent)
+    }
+    pub fn color(&self) -> Color {
+        match self {
+            BlocType::Dirt => Color::rgb(0.45, 0.31, 0.2),
+            BlocType::Grass => Color::rgb(0.36, 0.6, 0.25),
+            BlocType::Stone => Color::rgb(0.5, 0.5, 0.5),
+            BlocType::Sand => Color::rgb(0.86, 0.8, 0.56),
+            BlocType::SnowyDirt => Color::rgb(0.92, 0.94, 0.97),
+            BlocType::Air => Color::NONE,
+            BlocType::Glowstone => Color::rgb(0.95, 0.8, 0.42),
+            BlocType::Glass => Color::rgb(0.8, 0.9, 0.95),
+            BlocType::Leaves => Color::rgb(0.22, 0.45, 0.16)
+        }
+    }
 }
 
 impl Into<&str> for &BlocType {
@@ -51,7 +152,10 @@ impl Into<&str> for &BlocType {
             BlocType::Stone => "stone",
             BlocType::Sand => "sand",
             BlocType::SnowyDirt => "snowy_dirt",
-            BlocType::Air => "air"
+            BlocType::Air => "air",
+            BlocType::Glowstone => "glowstone",
+            BlocType::Glass => "glass",
+            BlocType::Leaves => "leaves"
         }
     }
 }
@@ -84,7 +188,7 @@ pub struct Neighbors {
     pub back: Option<Entity>
 }
 impl Neighbors {
-    fn get_with_direction(&self, direction:&Direction) -> Option<Entity> {
+    pub fn get_with_direction(&self, direction:&Direction) -> Option<Entity> {
         match direction {
             Direction::Up => self.up,
             Direction::Down => self.down,
@@ -119,23 +223,7 @@ impl Default for BlocFaces {
 }
 
 #[derive(Component)]
-pub struct FaceMarker;
-
-#[derive(Component)]
-pub struct BaseMaterial(pub Handle<StandardMaterial>);
-
-#[derive(Component)]
-pub struct NextMaterial(pub Option<Handle<StandardMaterial>>);
-
-#[derive(Component, PartialEq, Eq, Hash)]
-pub enum DestructionLevel {
-    Zero,
-    One,
-    Two,
-    Three,
-    Four,
-    Five
-}
+pub struct FaceMarker(pub Direction);
 
 #[derive(Bundle)]
 pub struct Bloc {
@@ -143,9 +231,7 @@ pub struct Bloc {
     neighbors: Neighbors,
     r#type: BlocType,
     faces: BlocFaces,
-    rigid_body: RigidBody,
-    spatial: SpatialBundle,
-    collision_groups: CollisionGroups
+    spatial: SpatialBundle
 }
 
 pub enum BlocTypeQuery<'a, 'b, 'world, 'state> {
@@ -165,32 +251,53 @@ impl BlocTypeQuery<'_, '_, '_, '_> {
     }
 }
 
+#[derive(Resource, Default)]
+pub struct BlocAssets {
+    quad: Handle<Mesh>,
+    materials: HashMap<(BlocType, Direction), Handle<StandardMaterial>>
+}
+impl BlocAssets {
+    pub fn material(&self, r#type: BlocType, direction: Direction) -> Handle<StandardMaterial> {
+        self.materials[&(r#type, direction)].clone()
+    }
+}
+
+pub fn setup_bloc_assets(
+    mut bloc_assets: ResMut<BlocAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>
+) {
+    bloc_assets.quad = meshes.add(Rectangle::new(SQUARE_UNIT, SQUARE_UNIT));
+    for r#type in BlocType::list() {
+        if r#type == BlocType::Air {
+            continue
+        }
+        for direction in Direction::list() {
+            let material = materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load(format!("{}/{}.png", r#type, direction.face_to_render_name()))),
+                alpha_mode: r#type.rendering().alpha_mode(),
+                ..default()
+            });
+            bloc_assets.materials.insert((r#type, direction), material);
+        }
+    }
+}
+
 pub fn render_bloc(
     bloc_entity: Entity,
     neighbors: &Neighbors,
     old_faces: &mut BlocFaces,
-    asset_server: &Res<AssetServer>,
     bloc_types_query: BlocTypeQuery,
-    meshes: &mut ResMut<'_, Assets<Mesh>>,
-    materials: &mut ResMut<'_, Assets<StandardMaterial>>,
-    cmds: &mut Commands,
-    // None: don't touch to the physic / Some<true>: render the physic / Some<false>: don't render the physic
-    overwrite_physic: Option<bool>
+    bloc_assets: &BlocAssets,
+    cmds: &mut Commands
 ) {
     let r#type = bloc_types_query.get(bloc_entity).unwrap();
     cmds.entity(bloc_entity).despawn_descendants();
-    // if let Some(false) = overwrite_physic {
-    //     cmds.entity(bloc_entity).remove::<Collider>();
-    // }
     if let BlocType::Air = r#type {
-        // if let Some(true) = overwrite_physic {
-        //     cmds.entity(bloc_entity).remove::<Collider>();
-        // }
         return
     }
     let mut faces: Vec<Entity> = Vec::new();
-    // Add collider for outside blocs only for optimization
-    let mut needs_new_collider = false;
     for direction in Direction::list() {
         let neighbor = match neighbors.get_with_direction(&direction) {
             Some(n) => bloc_types_query.get(n).unwrap(),
@@ -200,83 +307,46 @@ pub fn render_bloc(
                 continue
             }
         };
-        if neighbor != &BlocType::Air {
+        if neighbor.hides_face_of(*r#type) {
             continue
         }
-        needs_new_collider = overwrite_physic.unwrap_or(false);
-        // load the texture
-        let texture_handle = asset_server.load(&format!("{}/{}.png", r#type.to_string(), direction.face_to_render_name()));
-        
-        // create a new quad mesh. this is what we will apply the texture to
-        let quad_handle = meshes.add(
-            Mesh::from(
-                Rectangle::new(SQUARE_UNIT, SQUARE_UNIT)
-            )
-        );
-        let material_handle = materials.add(StandardMaterial {
-            base_color_texture: Some(texture_handle),
-            ..default()
-        });
         let (x, y, z) = direction.transform();
         let id = cmds.spawn((PbrBundle {
-            mesh: quad_handle.clone(),
-            material: material_handle.clone(),
+            mesh: bloc_assets.quad.clone(),
+            material: bloc_assets.material(*r#type, direction),
             transform: Transform::from_xyz(
                 x * SQUARE_UNIT,
                 y * SQUARE_UNIT,
                 z * SQUARE_UNIT
             ).looking_to(direction.looking_to(), Vec3::ZERO),
             ..default()
-        }, FaceMarker, DestructionLevel::Zero, BaseMaterial(material_handle), NextMaterial(None))).id();
+        }, FaceMarker(direction))).id();
         faces.push(id);
     }
-    let mut cmd = cmds.entity(bloc_entity);
-    cmd.push_children(&faces);
+    cmds.entity(bloc_entity).push_children(&faces);
     *old_faces = BlocFaces(faces);
-
-    // if needs_new_collider {
-    //     cmd.insert(Collider::cuboid(SQUARE_UNIT/2.0, SQUARE_UNIT/2.0, SQUARE_UNIT/2.0));
-    // }
 }
 
-pub fn load_physic(
-    bloc_entity: Entity,
+pub fn remove_bloc(
+    entity: Entity,
     neighbors: &Neighbors,
-    bloc_types_query: BlocTypeQuery,
-    cmds: &mut Commands
-) {
-    let r#type = bloc_types_query.get(bloc_entity).unwrap();
-    if let BlocType::Air = r#type {
-        cmds.entity(bloc_entity).remove::<Collider>();
-        return
-    }
-    // Add collider for outside blocs only for optimization
-    for direction in Direction::list() {
-        let neighbor = match neighbors.get_with_direction(&direction) {
-            Some(n) => bloc_types_query.get(n).unwrap(),
-            None => if direction == Direction::Up {
-                &BlocType::Air
-            } else {
-                continue
-            }
-        };
-        if neighbor != &BlocType::Air {
-            continue
-        }
-        cmds.entity(bloc_entity).insert(Collider::cuboid(SQUARE_UNIT/2.0, SQUARE_UNIT/2.0, SQUARE_UNIT/2.0));
-        return
-    }
-    cmds.entity(bloc_entity).remove::<Collider>();
-}
-pub fn unload_physic(
-    bloc_entity: Entity,
-    cmds: &mut Commands
+    blocs: &mut Query<(Entity,&mut Neighbors,&mut BlocFaces)>,
+    blocs_types_query: &mut Query<&mut BlocType>,
+    blocs_pos_parent_query: &Query<(&PosInChunk, &Parent), With<BlocType>>,
+    chunk_pos_query: &Query<&ChunkPos>,
+    game_state: &mut ResMut<GameState>,
+    chunk_saves: &mut ResMut<ChunkSaves>,
+    cmds: &mut Commands,
+    bloc_assets: &BlocAssets
 ) {
-    cmds.entity(bloc_entity).remove::<Collider>();
+    set_bloc(entity, BlocType::Air, neighbors, blocs, blocs_types_query, blocs_pos_parent_query, chunk_pos_query, game_state, chunk_saves, cmds, bloc_assets);
 }
 
-pub fn remove_bloc(
+/// Change the type of a bloc, save the change and render it again with its neighbors
+#[allow(clippy::too_many_arguments)]
+pub fn set_bloc(
     entity: Entity,
+    new_type: BlocType,
     neighbors: &Neighbors,
     blocs: &mut Query<(Entity,&mut Neighbors,&mut BlocFaces)>,
     blocs_types_query: &mut Query<&mut BlocType>,
@@ -285,52 +355,51 @@ pub fn remove_bloc(
     game_state: &mut ResMut<GameState>,
     chunk_saves: &mut ResMut<ChunkSaves>,
     cmds: &mut Commands,
-    asset_server: &Res<AssetServer>,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
+    bloc_assets: &BlocAssets
 ) {
     let (bloc_entity, mut neighbors_mut, mut faces) = blocs.get_mut(entity).unwrap();
-    *blocs_types_query.get_mut(entity).unwrap() = BlocType::Air;
-    render_bloc(bloc_entity, &mut neighbors_mut, &mut faces, asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, Some(true));
+    *blocs_types_query.get_mut(entity).unwrap() = new_type;
+    render_bloc(bloc_entity, &mut neighbors_mut, &mut faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
 
     let (pos, parent) = blocs_pos_parent_query.get(entity).unwrap();
     let chunk_pos = chunk_pos_query.get(parent.get()).unwrap();
-    game_state.chunks.get_mut(chunk_pos).unwrap().0[pos.to_chunk_index()] = BlocType::Air;
+    game_state.chunks.get_mut(chunk_pos).unwrap().0[pos.to_chunk_index()] = new_type;
+    cmds.entity(parent.get()).insert(ChunkPhysic::Dirty);
 
     match chunk_saves.0.get_mut(chunk_pos) {
         Some(entry) => {
-            entry.changes.insert(*pos, BlocType::Air);
+            entry.changes.insert(*pos, new_type);
         },
         None => {
             let mut entry = ChunkSave::default();
-            entry.changes.insert(*pos, BlocType::Air);
+            entry.changes.insert(*pos, new_type);
             chunk_saves.0.insert(*chunk_pos, entry);
         }
     }
 
     if let Some(n) = &neighbors.up {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
     if let Some(n) = &neighbors.down {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
     if let Some(n) = &neighbors.left {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
     if let Some(n) = &neighbors.right {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
     if let Some(n) = &neighbors.front {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
     if let Some(n) = &neighbors.back {
         let (n_bloc_entity, mut n_neighbors, mut n_faces) = blocs.get_mut(*n).unwrap();
-        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, &asset_server, BlocTypeQuery::Mut(blocs_types_query), meshes, materials, cmds, None);
+        render_bloc(n_bloc_entity, &mut n_neighbors, &mut n_faces, BlocTypeQuery::Mut(blocs_types_query), bloc_assets, cmds);
     }
 }
 
@@ -370,6 +439,22 @@ impl PosInChunk {
     pub fn to_neighbor(&self, dir: Direction) -> Self {
         dir.get_other_coordinates(self)
     }
+    // `None` above or under the world
+    pub fn from_translation(translation: Vec3) -> Option<Self> {
+        let bloc = (translation / SQUARE_UNIT).round();
+        if bloc.y < 0.0 || bloc.y >= CHUNK_Y as f32 {
+            return None
+        }
+        Some(Self {
+            x: (bloc.x as i32).rem_euclid(CHUNK_X as i32) as u8,
+            y: bloc.y as u8,
+            z: (bloc.z as i32).rem_euclid(CHUNK_Z as i32) as u8
+        })
+    }
+    /// World position of the bloc center
+    pub fn to_translation(&self, chunk_pos: ChunkPos) -> Vec3 {
+        Into::<Transform>::into(chunk_pos).translation + Into::<Transform>::into(*self).translation
+    }
 }
 
 /// Chunk position in chunk unit
@@ -379,6 +464,16 @@ pub struct ChunkPos {
     pub y: i32,
     pub z: i32
 }
+impl ChunkPos {
+    /// Chunk containing a world position
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            x: ((translation.x / SQUARE_UNIT) + 0.5).div_euclid(CHUNK_X as f32) as i32,
+            y: ((translation.y / SQUARE_UNIT) + 0.5).div_euclid(CHUNK_Y as f32) as i32,
+            z: ((translation.z / SQUARE_UNIT) + 0.5).div_euclid(CHUNK_Z as f32) as i32
+        }
+    }
+}
 impl Into<Pos> for ChunkPos {
     fn into(self) -> Pos {
         Pos {
@@ -394,7 +489,7 @@ impl Into<Transform> for ChunkPos {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Direction {
     Up, // +y
     Down, // -y
@@ -416,6 +511,20 @@ impl Direction {
         }
         new
     }
+    /// Direction of the face the `normal` vector points to (typically a ray hit point relative to the bloc center)
+    pub fn from_normal(normal: Vec3) -> Self {
+        let abs = normal.abs();
+        if abs.x >= abs.y && abs.x >= abs.z {
+            if normal.x > 0.0 { Direction::Right } else { Direction::Left }
+        } else if abs.y >= abs.z {
+            if normal.y > 0.0 { Direction::Up } else { Direction::Down }
+        } else {
+            if normal.z > 0.0 { Direction::Front } else { Direction::Back }
+        }
+    }
+    pub fn normal(&self) -> Vec3 {
+        -self.looking_to()
+    }
     fn face_to_render_name(&self) -> &'static str {
         match self {
             Direction::Up => "top",
@@ -481,7 +590,6 @@ impl ChunkBlocs {
                     let bloc = Bloc {
                         pos_in_chunk: pos_in_chunk.clone(),
                         spatial: SpatialBundle::from_transform(pos_in_chunk.into()),
-                        rigid_body: RigidBody::Fixed,
                         neighbors: Neighbors {
                             up: if y == (CHUNK_Y-1) as u8 {
                                 None
@@ -515,14 +623,9 @@ impl ChunkBlocs {
                             },
                         },
                         r#type: types[chunk_index],
-                        faces: BlocFaces::default(),
-                        collision_groups: CollisionGroups::new(BLOCS_PHYSIC_GROUP, Group::complement(BLOCS_PHYSIC_GROUP))
+                        faces: BlocFaces::default()
                     };
-                    let mut entity = cmds.get_entity(entities[chunk_index]).unwrap();
-                    entity.insert(bloc);
-                    if types[chunk_index] != BlocType::Air {
-                        entity.insert(Collider::cuboid(SQUARE_UNIT/2.0, SQUARE_UNIT/2.0, SQUARE_UNIT/2.0));
-                    }
+                    cmds.get_entity(entities[chunk_index]).unwrap().insert(bloc);
                 }
             }
         }
@@ -537,23 +640,51 @@ impl ChunkBlocs {
     pub fn set(&mut self, pos:&PosInChunk, val: Entity) {
         self.0[pos.to_chunk_index()] = val;
     }
-    pub fn render(&self, asset_server: &Res<AssetServer>, blocs: &mut Query<(Entity,&Neighbors,&mut BlocFaces)>, bloc_types_query: &Query<&BlocType>, meshes: &mut ResMut<'_, Assets<Mesh>>, materials: &mut ResMut<'_, Assets<StandardMaterial>>, cmds: &mut Commands,  physic_overwride: Option<bool>) {
+    pub fn render(&self, blocs: &mut Query<(Entity,&Neighbors,&mut BlocFaces)>, bloc_types_query: &Query<&BlocType>, bloc_assets: &BlocAssets, cmds: &mut Commands) {
         for bloc in self.0.iter() {
             let (bloc_entity, neighbors,mut faces) = blocs.get_mut(*bloc).expect("Cannot find bloc from chunk");
-            render_bloc(bloc_entity,  neighbors, &mut faces, asset_server, BlocTypeQuery::Simple(bloc_types_query), meshes, materials, cmds, physic_overwride);
+            render_bloc(bloc_entity,  neighbors, &mut faces, BlocTypeQuery::Simple(bloc_types_query), bloc_assets, cmds);
         }
     }
-    pub fn load_physic(&self, blocs: &Query<(Entity,&Neighbors)>, bloc_types_query: &Query<&BlocType>, cmds: &mut Commands) {
-        for bloc in self.0.iter() {
-            let (bloc_entity, neighbors) = blocs.get(*bloc).expect("Cannot find bloc from chunk");
-            load_physic(bloc_entity,  neighbors, BlocTypeQuery::Simple(bloc_types_query), cmds);
+    // `None` if the chunk is empty
+    pub fn build_collider(&self, bloc_types_query: &Query<&BlocType>) -> Option<Collider> {
+        let mut solid = [false; CHUNK_X*CHUNK_Y*CHUNK_Z];
+        for (i, bloc) in self.0.iter().enumerate() {
+            solid[i] = bloc_types_query.get(*bloc).is_ok_and(|t| *t != BlocType::Air);
         }
-    }
-    pub fn unload_physic(&self, cmds: &mut Commands) {
-        for bloc in self.0.iter() {
-            unload_physic(*bloc, cmds);
+        let shapes: Vec<_> = merge_solid_boxes(solid).into_iter().map(|(min, max)| {
+            let (min, max) = (min.as_vec3(), max.as_vec3());
+            let half_size = (max - min + Vec3::ONE) * SQUARE_UNIT / 2.0;
+            (
+                (min + max) * SQUARE_UNIT / 2.0,
+                Quat::IDENTITY,
+                Collider::cuboid(half_size.x, half_size.y, half_size.z)
+            )
+        }).collect();
+        if shapes.is_empty() {
+            None
+        } else {
+            Some(Collider::compound(shapes))
         }
     }
+    pub fn load_physic(&self, chunk: Entity, bloc_types_query: &Query<&BlocType>, cmds: &mut Commands) {
+        let mut cmd = cmds.entity(chunk);
+        match self.build_collider(bloc_types_query) {
+            Some(collider) => cmd.insert(collider),
+            None => cmd.remove::<Collider>()
+        };
+    }
+    pub fn unload_physic(&self, chunk: Entity, cmds: &mut Commands) {
+        cmds.entity(chunk).remove::<Collider>();
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkPhysic {
+    #[default]
+    Unloaded,
+    Loaded,
+    Dirty // a bloc changed since the collider was built
 }
 
 #[derive(Component, Clone, Copy)]
@@ -577,7 +708,10 @@ pub struct Chunk {
     spatial: SpatialBundle,
     blocs: ChunkBlocs,
     pos: ChunkPos,
-    neighbors_are_linked: ChunkNeighborsAreLinked
+    neighbors_are_linked: ChunkNeighborsAreLinked,
+    physic: ChunkPhysic,
+    rigid_body: RigidBody,
+    collision_groups: CollisionGroups
 }
 impl Chunk {
     pub fn new_empty(pos: ChunkPos, cmds: &mut Commands) -> Self {
@@ -585,7 +719,10 @@ impl Chunk {
             spatial: SpatialBundle::from_transform(pos.into()),
             pos,
             blocs: ChunkBlocs::new_empty(pos, cmds),
-            neighbors_are_linked: ChunkNeighborsAreLinked::default()
+            neighbors_are_linked: ChunkNeighborsAreLinked::default(),
+            physic: ChunkPhysic::Unloaded,
+            rigid_body: RigidBody::Fixed,
+            collision_groups: CollisionGroups::new(BLOCS_PHYSIC_GROUP, Group::complement(BLOCS_PHYSIC_GROUP))
         }
     }
     pub fn new_with_blocs(pos: ChunkPos, blocs: ChunkBlocs) -> Self {
@@ -593,20 +730,25 @@ impl Chunk {
             spatial: SpatialBundle::from_transform(pos.into()),
             pos,
             blocs,
-            neighbors_are_linked: ChunkNeighborsAreLinked::default()
+            neighbors_are_linked: ChunkNeighborsAreLinked::default(),
+            physic: ChunkPhysic::Unloaded,
+            rigid_body: RigidBody::Fixed,
+            collision_groups: CollisionGroups::new(BLOCS_PHYSIC_GROUP, Group::complement(BLOCS_PHYSIC_GROUP))
         }
     }
     pub fn get(&self, pos:&PosInChunk) -> Option<&Entity> {
         self.blocs.get(pos)
     }
-    pub fn render(&self, asset_server: &Res<AssetServer>, blocs: &mut Query<(Entity,&Neighbors,&mut BlocFaces)>, bloc_types_query: &Query<&BlocType>, meshes: &mut ResMut<'_, Assets<Mesh>>, materials: &mut ResMut<'_, Assets<StandardMaterial>>, cmds: &mut Commands, physic_overwride: Option<bool>) {
-        self.blocs.render(asset_server, blocs, bloc_types_query, meshes, materials, cmds, physic_overwride);
+    pub fn render(&self, blocs: &mut Query<(Entity,&Neighbors,&mut BlocFaces)>, bloc_types_query: &Query<&BlocType>, bloc_assets: &BlocAssets, cmds: &mut Commands) {
+        self.blocs.render(blocs, bloc_types_query, bloc_assets, cmds);
     }
 }
 
 pub trait Generator: Send + std::marker::Sync + 'static {
     fn new(seed: u32) -> Self;
     fn generate(&self, pos: ChunkPos) -> [BlocType; CHUNK_X*CHUNK_Y*CHUNK_Z];
+    /// Number of blocs between the bottom of the world and the surface at these absolute bloc coordinates, `None` if there is no ground
+    fn surface_height(&self, x: i32, z: i32) -> Option<u32>;
 }
 
 pub struct FlatWordGenerator;
@@ -634,6 +776,52 @@ impl Generator for FlatWordGenerator {
         types[PosInChunk { x:1, y:4, z:1 }.to_chunk_index()] = BlocType::Stone;
         return types
     }
+    fn surface_height(&self, x: i32, z: i32) -> Option<u32> {
+        if x.rem_euclid(CHUNK_X as i32) == 1 && z.rem_euclid(CHUNK_Z as i32) == 1 {
+            Some(5)
+        } else {
+            Some(4)
+        }
+    }
+}
+
+// Boxes are given by their first and last bloc
+pub fn merge_solid_boxes(mut solid: [bool; CHUNK_X*CHUNK_Y*CHUNK_Z]) -> Vec<(IVec3, IVec3)> {
+    let index = |x: usize, y: usize, z: usize| PosInChunk { x: x as u8, y: y as u8, z: z as u8 }.to_chunk_index();
+
+    let mut boxes = Vec::new();
+    for i in 0..solid.len() {
+        if !solid[i] {
+            continue
+        }
+        let start = PosInChunk::from_chunk_index(i);
+        let (x0, y0, z0) = (start.x as usize, start.y as usize, start.z as usize);
+        // Grow the box along x, then z, then y while every bloc in it is solid
+        let mut x1 = x0;
+        while x1+1 < CHUNK_X && solid[index(x1+1, y0, z0)] {
+            x1 += 1;
+        }
+        let mut z1 = z0;
+        while z1+1 < CHUNK_Z && (x0..=x1).all(|x| solid[index(x, y0, z1+1)]) {
+            z1 += 1;
+        }
+        let mut y1 = y0;
+        while y1+1 < CHUNK_Y && (x0..=x1).all(|x| (z0..=z1).all(|z| solid[index(x, y1+1, z)])) {
+            y1 += 1;
+        }
+        for x in x0..=x1 {
+            for y in y0..=y1 {
+                for z in z0..=z1 {
+                    solid[index(x, y, z)] = false;
+                }
+            }
+        }
+        boxes.push((
+            IVec3::new(x0 as i32, y0 as i32, z0 as i32),
+            IVec3::new(x1 as i32, y1 as i32, z1 as i32)
+        ));
+    }
+    boxes
 }
 
 #[derive(Resource)]
@@ -654,6 +842,14 @@ impl<G: Generator> Chunks<G> {
     pub fn get(&self, pos: ChunkPos) -> Option<&Entity> {
         self.inner.get(&pos)
     }
+    pub fn get_bloc(&self, chunk_pos: ChunkPos, pos: &PosInChunk, chunks_query: &Query<&ChunkBlocs>) -> Option<Entity> {
+        let chunk = self.get(chunk_pos)?;
+        chunks_query.get(*chunk).ok()?.get(pos).copied()
+    }
+    pub fn bloc_at(&self, translation: Vec3, chunks_query: &Query<&ChunkBlocs>) -> Option<Entity> {
+        let pos = PosInChunk::from_translation(translation)?;
+        self.get_bloc(ChunkPos { y: 0, ..ChunkPos::from_translation(translation) }, &pos, chunks_query)
+    }
     pub fn clear(&mut self, cmds: &mut Commands) {
         for (_, entity) in self.inner.iter() {
             cmds.entity(*entity).despawn_recursive();
@@ -680,17 +876,21 @@ impl<G: Generator> Chunks<G> {
         let types = game_state.chunks.get(&pos).unwrap().0;
         self.load_types(pos, &types, cmds);
     }
-    pub fn generate(&mut self, pos: ChunkPos, chunk_saves: &ChunkSaves, game_state: &mut GameState, cmds: &mut Commands) {
-        // return if there is already a chunk
-        if let Some(_) = self.get(pos) {
-            return
-        }
+    pub fn generate_types(&self, pos: ChunkPos, chunk_saves: &ChunkSaves) -> [BlocType; CHUNK_X*CHUNK_Y*CHUNK_Z] {
         let mut types = self.generator.generate(pos);
         if let Some(save) = chunk_saves.0.get(&pos) {
             for (pos, r#type) in save.changes.iter() {
                 types[pos.to_chunk_index()] = *r#type;
             }
         }
+        types
+    }
+    pub fn generate(&mut self, pos: ChunkPos, chunk_saves: &ChunkSaves, game_state: &mut GameState, cmds: &mut Commands) {
+        // return if there is already a chunk
+        if let Some(_) = self.get(pos) {
+            return
+        }
+        let types = self.generate_types(pos, chunk_saves);
         game_state.chunks.insert(pos, ChunkTypes(types));
         self.load_types(pos, &types, cmds);
     }
@@ -701,12 +901,7 @@ impl<G: Generator> Chunks<G> {
         let types = match game_state.chunks.get(&pos) {
             Some(types) => types.0,
             None => {
-                let mut types = self.generator.generate(pos);
-                if let Some(save) = chunk_saves.0.get(&pos) {
-                    for (pos, r#type) in save.changes.iter() {
-                        types[pos.to_chunk_index()] = *r#type;
-                    }
-                }
+                let types = self.generate_types(pos, chunk_saves);
                 game_state.chunks.insert(pos, ChunkTypes(types));
                 types
             }
@@ -782,7 +977,7 @@ impl<G: Generator> Chunks<G> {
     /// * Fill the neighbors of the edge blocs for each chunk
     /// * /!\ This assumes that the blocs are already spawned and that the chunks are neighbors
     /// * Errors if the chunks are not spawned
-    pub fn link(&self, pos1: ChunkPos, pos2: ChunkPos, blocs1: &ChunkBlocs, blocs2: &ChunkBlocs, blocs_query: &mut Query<(&mut Neighbors, &mut BlocFaces)>, asset_server: &Res<AssetServer>, bloc_types_query: &Query<&BlocType>, meshes: &mut ResMut<Assets<Mesh>>, materials: &mut ResMut<Assets<StandardMaterial>>, cmds: &mut Commands) {
+    pub fn link(&self, pos1: ChunkPos, pos2: ChunkPos, blocs1: &ChunkBlocs, blocs2: &ChunkBlocs, blocs_query: &mut Query<(&mut Neighbors, &mut BlocFaces)>, bloc_types_query: &Query<&BlocType>, bloc_assets: &BlocAssets, cmds: &mut Commands) {
         let x_iter = if pos1.x < pos2.x {
             (CHUNK_X as u8-1..=CHUNK_X as u8-1).into_iter().zip(0..=0)
         } else if pos1.x > pos2.x {
@@ -831,8 +1026,8 @@ impl<G: Generator> Chunks<G> {
                         blocs[0].0.back = Some(entities.1);
                         blocs[1].0.front = Some(entities.0);
                     }
-                    render_bloc(entities.0, &blocs[0].0, &mut blocs[0].1, asset_server, BlocTypeQuery::Simple(bloc_types_query), meshes, materials, cmds, None);
-                    render_bloc(entities.1, &blocs[1].0, &mut blocs[1].1, asset_server, BlocTypeQuery::Simple(bloc_types_query), meshes, materials, cmds, None)
+                    render_bloc(entities.0, &blocs[0].0, &mut blocs[0].1, BlocTypeQuery::Simple(bloc_types_query), bloc_assets, cmds);
+                    render_bloc(entities.1, &blocs[1].0, &mut blocs[1].1, BlocTypeQuery::Simple(bloc_types_query), bloc_assets, cmds)
                 }
             }
         }
@@ -892,29 +1087,13 @@ impl<G: Generator> Chunks<G> {
     }
 }
 
-pub fn apply_next_material(
-    mut faces: Query<(&mut Handle<StandardMaterial>, &mut NextMaterial, &BaseMaterial), With<FaceMarker>>,
-    asset_server: Res<AssetServer>
-) {
-    for (mut face, mut next_mat, base_mat) in faces.iter_mut() {
-        if let Some(nm) = &next_mat.0 {
-            if asset_server.is_loaded_with_dependencies(nm.id()) || nm == &base_mat.0 {
-                *face = nm.clone();
-                next_mat.0 = None;
-            }
-        }
-    }
-}
-
 pub fn link_chunks<G: Generator>(
     chunks: Res<Chunks<G>>,
     mut nal_query: Query<(Entity, &mut ChunkNeighborsAreLinked)>,
     chunks_query: Query<(&ChunkPos, &ChunkBlocs)>,
     mut blocs_query: Query<(&mut Neighbors, &mut BlocFaces)>,
-    asset_server: Res<AssetServer>,
     bloc_types_query: Query<&BlocType>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    bloc_assets: Res<BlocAssets>,
     mut cmds: Commands
 ) {
     for (id, mut nal) in nal_query.iter_mut() {
@@ -957,7 +1136,7 @@ pub fn link_chunks<G: Generator>(
                 Ok(x) => x,
                 Err(_) => continue
             };
-            chunks.link(*pos1, pos2, blocs1, blocs2, &mut blocs_query, &asset_server, &bloc_types_query, &mut meshes, &mut materials, &mut cmds);
+            chunks.link(*pos1, pos2, blocs1, blocs2, &mut blocs_query, &bloc_types_query, &bloc_assets, &mut cmds);
             match val_to_change {
                 0 => nal.up = true,
                 1 => nal.right = true,
@@ -966,4 +1145,60 @@ pub fn link_chunks<G: Generator>(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_pos_from_translation() {
+        let chunk = |x: f32, y: f32, z: f32| ChunkPos::from_translation(Vec3::new(x, y, z)*SQUARE_UNIT);
+        assert_eq!(chunk(0.0, 0.0, 0.0), ChunkPos { x: 0, y: 0, z: 0 });
+        // The bloc at 0 goes from -0.5 to 0.5
+        assert_eq!(chunk(-0.4, 3.0, -0.4), ChunkPos { x: 0, y: 0, z: 0 });
+        assert_eq!(chunk(-0.6, 3.0, -0.6), ChunkPos { x: -1, y: 0, z: -1 });
+        assert_eq!(chunk(CHUNK_X as f32 - 0.6, 3.0, 0.0), ChunkPos { x: 0, y: 0, z: 0 });
+        assert_eq!(chunk(CHUNK_X as f32 - 0.4, 3.0, 0.0), ChunkPos { x: 1, y: 0, z: 0 });
+        assert_eq!(chunk(0.0, CHUNK_Y as f32 + 2.0, -3.0*CHUNK_Z as f32), ChunkPos { x: 0, y: 1, z: -3 });
+    }
+
+    // Panics if two boxes overlap
+    fn covered(boxes: &[(IVec3, IVec3)]) -> [bool; CHUNK_X*CHUNK_Y*CHUNK_Z] {
+        let mut covered = [false; CHUNK_X*CHUNK_Y*CHUNK_Z];
+        for (min, max) in boxes {
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    for z in min.z..=max.z {
+                        let i = PosInChunk { x: x as u8, y: y as u8, z: z as u8 }.to_chunk_index();
+                        assert!(!covered[i], "boxes overlap");
+                        covered[i] = true;
+                    }
+                }
+            }
+        }
+        covered
+    }
+
+    #[test]
+    fn merge_solid_boxes_of_empty_and_full_chunks() {
+        assert!(merge_solid_boxes([false; CHUNK_X*CHUNK_Y*CHUNK_Z]).is_empty());
+        assert_eq!(
+            merge_solid_boxes([true; CHUNK_X*CHUNK_Y*CHUNK_Z]),
+            vec![(IVec3::ZERO, IVec3::new(CHUNK_X as i32 - 1, CHUNK_Y as i32 - 1, CHUNK_Z as i32 - 1))]
+        );
+    }
+
+    #[test]
+    fn merge_solid_boxes_covers_exactly_the_solid_blocs() {
+        let mut solid = [false; CHUNK_X*CHUNK_Y*CHUNK_Z];
+        for (i, solid) in solid.iter_mut().enumerate() {
+            let pos = PosInChunk::from_chunk_index(i);
+            // Ground with a pillar and a hole
+            *solid = (pos.y < 5 && !(pos.x == 4 && pos.z == 1)) || (pos.x == 2 && pos.z == 3 && pos.y < 9);
+        }
+        let boxes = merge_solid_boxes(solid);
+        assert_eq!(covered(&boxes), solid);
+        assert!(boxes.len() < 10);
+    }
+}
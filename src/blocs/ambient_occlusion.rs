@@ -0,0 +1,37 @@
+use bevy::prelude::*;
+use crate::{blocs::*, blocs::Direction, GameState};
+
+// From fully occluded to open
+pub const AO_BRIGHTNESS: [f32; 4] = [0.45, 0.65, 0.82, 1.0];
+
+// In vertex order
+pub const QUAD_CORNERS: [[f32; 2]; 4] = [[1.0, 1.0], [-1.0, 1.0], [-1.0, -1.0], [1.0, -1.0]];
+
+fn is_solid(game_state: &GameState, bloc: IVec3) -> bool {
+    bloc_type_at(game_state, bloc).is_some_and(|t| t.is_opaque())
+}
+
+// 0 to 3, 3 is open
+pub fn face_ambient_occlusion(game_state: &GameState, bloc: IVec3, face: Direction, face_transform: &Transform) -> [u8; 4] {
+    let normal = face.normal().round().as_ivec3();
+    let front = bloc + normal;
+    QUAD_CORNERS.map(|[x, y]| {
+        // Corner of the bloc this vertex is at, each component is -1 or 1
+        let corner = face_transform.transform_point(Vec3::new(x, y, 0.0)*(SQUARE_UNIT/2.0))/(SQUARE_UNIT/2.0);
+        let corner = corner.round().as_ivec3();
+        let tangent = corner - normal*corner.dot(normal);
+        let (side_1, side_2) = if normal.x != 0 {
+            (IVec3::new(0, tangent.y, 0), IVec3::new(0, 0, tangent.z))
+        } else if normal.y != 0 {
+            (IVec3::new(tangent.x, 0, 0), IVec3::new(0, 0, tangent.z))
+        } else {
+            (IVec3::new(tangent.x, 0, 0), IVec3::new(0, tangent.y, 0))
+        };
+        let side_1 = is_solid(game_state, front + side_1);
+        let side_2 = is_solid(game_state, front + side_2);
+        if side_1 && side_2 {
+            return 0
+        }
+        3 - side_1 as u8 - side_2 as u8 - is_solid(game_state, front + tangent) as u8
+    })
+}
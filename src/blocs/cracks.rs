@@ -1,25 +1,87 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::render_resource::{AsBindGroup, ShaderRef}};
+use crate::SQUARE_UNIT;
+
+pub const CRACK_STAGES: usize = 5;
+// Slightly bigger than the bloc so it is drawn over its faces
+const OVERLAY_SCALE: f32 = 1.002;
 
 pub struct CracksPlugin;
 impl Plugin for CracksPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<Cracks>()
-            .add_systems(Startup, setup_cracks);
+        app.add_plugins(MaterialPlugin::<CrackMaterial>::default())
+            .init_resource::<Cracks>()
+            .add_systems(Startup, setup_cracks)
+            .add_systems(Update, build_cracks_texture);
+    }
+}
+
+#[derive(Asset, TypePath, AsBindGroup, Clone)]
+pub struct CrackMaterial {
+    // Stage in `x`, the other components keep the 16 bytes alignment required by WebGL2
+    #[uniform(0)]
+    pub stage: UVec4,
+    #[texture(1, dimension = "2d_array")]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>
+}
+impl Material for CrackMaterial {
+    fn fragment_shader() -> ShaderRef {
+        "shaders/cracks.wgsl".into()
+    }
+    fn alpha_mode(&self) -> AlphaMode {
+        AlphaMode::Blend
     }
 }
 
 #[derive(Resource, Default)]
-pub struct Cracks (pub [Handle<Image>; 5]);
+pub struct Cracks {
+    // Until they are stacked in the texture array
+    stages: Vec<Handle<Image>>,
+    pub material: Handle<CrackMaterial>
+}
+
+#[derive(Component)]
+pub struct CrackOverlay;
 
 pub fn setup_cracks(
     asset_server: Res<AssetServer>,
-    mut cracks: ResMut<Cracks>
+    mut cracks: ResMut<Cracks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CrackMaterial>>,
+    mut cmds: Commands
+) {
+    cracks.stages = (1..=CRACK_STAGES).map(|i| asset_server.load(format!("cracks/crack_{}.png", i))).collect();
+    cracks.material = materials.add(CrackMaterial {
+        stage: UVec4::ZERO,
+        texture: None
+    });
+    cmds.spawn((MaterialMeshBundle {
+        mesh: meshes.add(Cuboid::from_size(Vec3::splat(SQUARE_UNIT*OVERLAY_SCALE))),
+        material: cracks.material.clone(),
+        visibility: Visibility::Hidden,
+        ..default()
+    }, CrackOverlay));
+}
+
+pub fn build_cracks_texture(
+    mut cracks: ResMut<Cracks>,
+    mut images: ResMut<Assets<Image>>,
+    mut materials: ResMut<Assets<CrackMaterial>>
 ) {
-    cracks.0 = [
-        asset_server.load("cracks/crack_1.png"),
-        asset_server.load("cracks/crack_2.png"),
-        asset_server.load("cracks/crack_3.png"),
-        asset_server.load("cracks/crack_4.png"),
-        asset_server.load("cracks/crack_5.png")
-    ];
-}
\ No newline at end of file
+    if cracks.stages.is_empty() || cracks.stages.iter().any(|stage| images.get(stage).is_none()) {
+        return
+    }
+    let stages = std::mem::take(&mut cracks.stages);
+    let mut texture = images.get(&stages[0]).unwrap().clone();
+    for stage in stages[1..].iter() {
+        let stage = images.get(stage).unwrap();
+        if stage.size() != texture.size() || stage.texture_descriptor.format != texture.texture_descriptor.format {
+            error!("All the crack stages must have the same size and format");
+            return
+        }
+        texture.data.extend_from_slice(&stage.data);
+    }
+    texture.texture_descriptor.size.height *= CRACK_STAGES as u32;
+    texture.reinterpret_stacked_2d_as_array(CRACK_STAGES as u32);
+    materials.get_mut(&cracks.material).unwrap().texture = Some(images.add(texture));
+}
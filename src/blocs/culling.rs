@@ -0,0 +1,136 @@
+use bevy::{prelude::*, pbr::{FogFalloff, FogSettings}, render::{primitives::{Aabb, Frustum}, view::VisibilitySystems}, utils::{HashMap, HashSet}};
+use crate::{blocs::*, ChunkTypes, GameState, HeadMarker};
+
+const FOG_START: f32 = 0.5;
+
+pub struct CullingPlugin;
+impl Plugin for CullingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkOpacity>()
+            .add_systems(PostUpdate, update_chunk_opacity)
+            .add_systems(PostUpdate, cull_chunks
+                .after(update_chunk_opacity)
+                .after(VisibilitySystems::UpdatePerspectiveFrusta)
+                .before(VisibilitySystems::VisibilityPropagate)
+            );
+    }
+}
+
+// The player can be anywhere in its chunk, so the last ring of LOD chunks is not always there
+pub fn fog_distance() -> f32 {
+    (LOD_DISTANCE - 1) as f32 * CHUNK_X.min(CHUNK_Z) as f32 * SQUARE_UNIT
+}
+
+pub fn distance_fog() -> FogSettings {
+    FogSettings {
+        falloff: FogFalloff::Linear {
+            start: fog_distance()*FOG_START,
+            end: fog_distance()
+        },
+        ..default()
+    }
+}
+
+pub fn chunk_aabb() -> Aabb {
+    Aabb::from_min_max(
+        Vec3::splat(-SQUARE_UNIT/2.0),
+        Vec3::new(CHUNK_X as f32, CHUNK_Y as f32, CHUNK_Z as f32)*SQUARE_UNIT - SQUARE_UNIT/2.0
+    )
+}
+
+// Whether each side of a chunk (in the order of `NEIGHBORS`) is only made of opaque blocs
+#[derive(Resource, Default)]
+pub struct ChunkOpacity(HashMap<ChunkPos, [bool; 6]>);
+impl ChunkOpacity {
+    fn opaque_sides(types: &ChunkTypes) -> [bool; 6] {
+        NEIGHBORS.map(|normal| {
+            let last = IVec3::new(CHUNK_X as i32, CHUNK_Y as i32, CHUNK_Z as i32) - 1;
+            let axis = normal.abs();
+            // Bloc coordinates in the side are between `min` and `max`
+            let (min, max) = if normal.cmpgt(IVec3::ZERO).any() {
+                (last*axis, last)
+            } else {
+                (IVec3::ZERO, last*(1 - axis))
+            };
+            (min.x..=max.x).all(|x| (min.y..=max.y).all(|y| (min.z..=max.z).all(|z| {
+                types.0[PosInChunk { x: x as u8, y: y as u8, z: z as u8 }.to_chunk_index()].is_opaque()
+            })))
+        })
+    }
+    fn side_is_closed(&self, chunk_pos: ChunkPos, side: usize) -> bool {
+        let normal = NEIGHBORS[side];
+        let neighbor = ChunkPos {
+            x: chunk_pos.x + normal.x,
+            y: chunk_pos.y + normal.y,
+            z: chunk_pos.z + normal.z
+        };
+        // The opposite side is the next one in `NEIGHBORS`
+        let opposite = side ^ 1;
+        match (self.0.get(&chunk_pos), self.0.get(&neighbor)) {
+            (Some(sides), Some(neighbor_sides)) => sides[side] && neighbor_sides[opposite],
+            // Nothing can see a chunk from under the world
+            (Some(sides), None) => sides[side] && neighbor.y < 0,
+            _ => false
+        }
+    }
+}
+
+pub fn update_chunk_opacity(
+    mut opacity: ResMut<ChunkOpacity>,
+    game_state: Res<GameState>,
+    changed_blocs: Query<&Parent, Changed<BlocType>>,
+    chunk_pos_query: Query<&ChunkPos>,
+    mut ev_render: EventReader<Render>
+) {
+    // A loaded save refills the chunks under the same positions
+    let reload = ev_render.read().count() > 0;
+    opacity.0.retain(|pos, _| !reload && game_state.chunks.contains_key(pos));
+    let mut outdated: HashSet<ChunkPos> = game_state.chunks.keys().filter(|pos| !opacity.0.contains_key(*pos)).copied().collect();
+    outdated.extend(changed_blocs.iter().filter_map(|parent| chunk_pos_query.get(parent.get()).ok()).copied());
+    for chunk_pos in outdated {
+        if let Some(types) = game_state.chunks.get(&chunk_pos) {
+            opacity.0.insert(chunk_pos, ChunkOpacity::opaque_sides(types));
+        }
+    }
+}
+
+pub fn cull_chunks(
+    head: Query<(&GlobalTransform, &Frustum), With<HeadMarker>>,
+    mut chunks: Query<(&ChunkPos, &GlobalTransform, &mut Visibility), With<ChunkBlocs>>,
+    opacity: Res<ChunkOpacity>
+) {
+    let (head, frustum) = match head.get_single() {
+        Ok(head) => head,
+        Err(_) => return
+    };
+    let eye = head.translation();
+    let aabb = chunk_aabb();
+    let max_distance = fog_distance() + aabb.half_extents.length();
+    for (chunk_pos, transform, mut visibility) in chunks.iter_mut() {
+        let center = transform.transform_point(aabb.center.into());
+        let visible = center.distance(eye) <= max_distance
+            && frustum.intersects_obb(&aabb, &transform.affine(), true, true)
+            && !is_enclosed(*chunk_pos, eye - transform.translation(), &aabb, &opacity);
+        let new = if visible { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != new {
+            *visibility = new;
+        }
+    }
+}
+
+// `eye` is in the local space of the chunk
+fn is_enclosed(chunk_pos: ChunkPos, eye: Vec3, aabb: &Aabb, opacity: &ChunkOpacity) -> bool {
+    let min = Vec3::from(aabb.min());
+    let max = Vec3::from(aabb.max());
+    let mut facing_the_eye = NEIGHBORS.iter().enumerate().filter(|(_, normal)| {
+        let normal = normal.as_vec3();
+        // Signed distance from the plane of the side to the eye
+        let plane = if normal.cmpgt(Vec3::ZERO).any() { max } else { min };
+        (eye - plane).dot(normal) > 0.0
+    }).peekable();
+    // The eye is in the chunk
+    if facing_the_eye.peek().is_none() {
+        return false
+    }
+    facing_the_eye.all(|(side, _)| opacity.side_is_closed(chunk_pos, side))
+}
@@ -1,7 +1,9 @@
 use bevy::prelude::*;
-use super::{Generator as GeneratorTrait, BlocType, CHUNK_X, CHUNK_Y, CHUNK_Z, ChunkPos, PosInChunk};
-use noise::{Fbm, Perlin};
-use noise::utils::{NoiseMapBuilder, PlaneMapBuilder};
+use super::{Generator as GeneratorTrait, BlocType, CHUNK_X, CHUNK_Y, CHUNK_Z, ChunkPos, PosInChunk, Pos};
+use noise::{Fbm, NoiseFn, Perlin};
+
+/// Noise units per bloc on the horizontal axes. Noise is sampled at absolute world bloc coordinates scaled by this value, so the terrain doesn't depend on the chunk size
+pub const HORIZONTAL_SCALE: f64 = 0.125;
 
 pub enum Biome {
     Plain,
@@ -42,6 +44,8 @@ impl Biome {
 
 pub struct Generator {
     seed: u32,
+    /// Noise units per bloc, see `HORIZONTAL_SCALE`
+    pub horizontal_scale: f64,
     height_noise: Fbm<Perlin>,
     temp_noise: Fbm<Perlin>,
     rain_noise: Fbm<Perlin>
@@ -59,37 +63,20 @@ impl GeneratorTrait for Generator {
         rain_noise.octaves = 1;
         Self {
             seed,
+            horizontal_scale: HORIZONTAL_SCALE,
             height_noise,
             temp_noise,
             rain_noise
         }
     }
     fn generate(&self, pos: ChunkPos) -> [BlocType; CHUNK_X*CHUNK_Y*CHUNK_Z] {
-        let height_noise_map = PlaneMapBuilder::<_, 2>::new(&self.height_noise)
-            .set_size(CHUNK_X, CHUNK_Z)
-            .set_x_bounds((pos.x as f64) - 0.5, (pos.x as f64) + 0.5)
-            .set_y_bounds((pos.z as f64) - 0.5, (pos.z as f64) + 0.5)
-            .build();
-        let temp_noise_map = PlaneMapBuilder::<_, 2>::new(&self.temp_noise)
-            .set_size(CHUNK_X, CHUNK_Z)
-            .set_x_bounds((pos.x as f64) - 0.5, (pos.x as f64) + 0.5)
-            .set_y_bounds((pos.z as f64) - 0.5, (pos.z as f64) + 0.5)
-            .build();
-        let rain_noise_map = PlaneMapBuilder::<_, 2>::new(&self.rain_noise)
-            .set_size(CHUNK_X, CHUNK_Z)
-            .set_x_bounds((pos.x as f64) - 0.5, (pos.x as f64) + 0.5)
-            .set_y_bounds((pos.z as f64) - 0.5, (pos.z as f64) + 0.5)
-            .build();
+        let origin: Pos = pos.into();
 
         let mut types = [BlocType::Air; CHUNK_X*CHUNK_Y*CHUNK_Z];
         for x in 0..CHUNK_X as u8 {
             for z in 0..CHUNK_Z as u8 {
-                let temp = ((temp_noise_map.get_value(x as usize, z as usize) + 1.0)/2.0) as f32;
-                let rain = ((rain_noise_map.get_value(x as usize, z as usize) + 1.0)/2.0) as f32;
-                let biome = Biome::new(temp, rain);
-                let mut h = Biome::avg_height(temp, rain);
-                h += ((height_noise_map.get_value(x as usize, z as usize) + 1.0)/2.0) as f32 * (CHUNK_Y as f32) * Biome::height_variance(temp, rain);
-                let h = (h as u8).min(CHUNK_Y as u8);
+                // Sample by absolute bloc coordinates so that neighbor chunks are continuous
+                let (biome, h) = self.column(origin.x + x as i32, origin.z + z as i32);
                 for y in 0..h.saturating_sub(2) {
                     types[PosInChunk { x,y,z }.to_chunk_index()] = BlocType::Stone;
                 }
@@ -99,4 +86,23 @@ impl GeneratorTrait for Generator {
         }
         types
     }
+    fn surface_height(&self, x: i32, z: i32) -> Option<u32> {
+        // The top bloc is always placed, even when the height is 0
+        Some(self.column(x, z).1.max(1) as u32)
+    }
+}
+impl Generator {
+    /// Biome and terrain height of the column at these absolute bloc coordinates
+    fn column(&self, x: i32, z: i32) -> (Biome, u8) {
+        let point = [
+            x as f64 * self.horizontal_scale,
+            z as f64 * self.horizontal_scale
+        ];
+        let temp = ((self.temp_noise.get(point) + 1.0)/2.0) as f32;
+        let rain = ((self.rain_noise.get(point) + 1.0)/2.0) as f32;
+        let biome = Biome::new(temp, rain);
+        let mut h = Biome::avg_height(temp, rain);
+        h += ((self.height_noise.get(point) + 1.0)/2.0) as f32 * (CHUNK_Y as f32) * Biome::height_variance(temp, rain);
+        (biome, (h as u8).min(CHUNK_Y as u8))
+    }
 }
\ No newline at end of file
@@ -0,0 +1,352 @@
+use std::collections::VecDeque;
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use crate::{blocs::*, GameState};
+
+pub const MAX_LIGHT: u8 = 15;
+
+pub const NEIGHBORS: [IVec3; 6] = [IVec3::Y, IVec3::NEG_Y, IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z];
+
+pub struct LightPlugin;
+impl Plugin for LightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LightMap>()
+            .add_systems(PostUpdate, update_light.before(bake_face_shading));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightChannel {
+    Sky, // goes down without fading
+    Bloc
+}
+
+#[derive(Clone)]
+pub struct ChunkLight {
+    sky: [u8; CHUNK_X*CHUNK_Y*CHUNK_Z],
+    bloc: [u8; CHUNK_X*CHUNK_Y*CHUNK_Z]
+}
+impl Default for ChunkLight {
+    fn default() -> Self {
+        Self {
+            sky: [0; CHUNK_X*CHUNK_Y*CHUNK_Z],
+            bloc: [0; CHUNK_X*CHUNK_Y*CHUNK_Z]
+        }
+    }
+}
+impl ChunkLight {
+    fn channel(&self, channel: LightChannel) -> &[u8; CHUNK_X*CHUNK_Y*CHUNK_Z] {
+        match channel {
+            LightChannel::Sky => &self.sky,
+            LightChannel::Bloc => &self.bloc
+        }
+    }
+    fn channel_mut(&mut self, channel: LightChannel) -> &mut [u8; CHUNK_X*CHUNK_Y*CHUNK_Z] {
+        match channel {
+            LightChannel::Sky => &mut self.sky,
+            LightChannel::Bloc => &mut self.bloc
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LightMap {
+    chunks: HashMap<ChunkPos, ChunkLight>,
+    pub changed: HashSet<IVec3>
+}
+impl LightMap {
+    // `None` under the world or if its chunk has no light yet
+    fn try_get(&self, channel: LightChannel, bloc: IVec3) -> Option<u8> {
+        if bloc.y >= CHUNK_Y as i32 {
+            return Some(match channel {
+                LightChannel::Sky => MAX_LIGHT,
+                LightChannel::Bloc => 0
+            })
+        }
+        let (chunk_pos, pos) = split_bloc_coordinates(bloc)?;
+        Some(self.chunks.get(&chunk_pos)?.channel(channel)[pos.to_chunk_index()])
+    }
+    pub fn get(&self, channel: LightChannel, bloc: IVec3) -> u8 {
+        match self.try_get(channel, bloc) {
+            Some(light) => light,
+            // Don't darken the borders of the loaded world
+            None if bloc.y >= 0 && channel == LightChannel::Sky => MAX_LIGHT,
+            None => 0
+        }
+    }
+    pub fn level(&self, bloc: IVec3) -> u8 {
+        self.get(LightChannel::Sky, bloc).max(self.get(LightChannel::Bloc, bloc))
+    }
+    fn set(&mut self, channel: LightChannel, bloc: IVec3, light: u8) {
+        let (chunk_pos, pos) = match split_bloc_coordinates(bloc) {
+            Some(coordinates) => coordinates,
+            None => return
+        };
+        if let Some(chunk) = self.chunks.get_mut(&chunk_pos) {
+            let old = std::mem::replace(&mut chunk.channel_mut(channel)[pos.to_chunk_index()], light);
+            if old != light {
+                self.changed.insert(bloc);
+            }
+        }
+    }
+
+    fn propagate(&mut self, game_state: &GameState, channel: LightChannel, mut queue: VecDeque<IVec3>) {
+        while let Some(bloc) = queue.pop_front() {
+            let light = match self.try_get(channel, bloc) {
+                Some(light) if light > 1 => light,
+                _ => continue
+            };
+            for dir in NEIGHBORS {
+                let neighbor = bloc + dir;
+                let current = match self.try_get(channel, neighbor) {
+                    Some(current) if neighbor.y < CHUNK_Y as i32 => current,
+                    _ => continue
+                };
+                if bloc_type_at(game_state, neighbor).is_none_or(|t| t.is_opaque()) {
+                    continue
+                }
+                let new = if channel == LightChannel::Sky && dir == IVec3::NEG_Y && light == MAX_LIGHT {
+                    MAX_LIGHT
+                } else {
+                    light - 1
+                };
+                if current < new {
+                    self.set(channel, neighbor, new);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+    }
+
+    // Returns the blocs around the darkened area that must spread their light again
+    fn remove(&mut self, game_state: &GameState, channel: LightChannel, bloc: IVec3) -> VecDeque<IVec3> {
+        let mut relight = VecDeque::new();
+        let light = match self.try_get(channel, bloc) {
+            Some(light) if bloc.y < CHUNK_Y as i32 => light,
+            _ => return relight
+        };
+        self.set(channel, bloc, 0);
+        let mut queue = VecDeque::from([(bloc, light)]);
+        while let Some((bloc, light)) = queue.pop_front() {
+            for dir in NEIGHBORS {
+                let neighbor = bloc + dir;
+                let current = match self.try_get(channel, neighbor) {
+                    Some(current) if current > 0 && neighbor.y < CHUNK_Y as i32 => current,
+                    _ => continue
+                };
+                let from_this_bloc = current < light
+                    || (channel == LightChannel::Sky && dir == IVec3::NEG_Y && light == MAX_LIGHT);
+                if from_this_bloc {
+                    self.set(channel, neighbor, 0);
+                    queue.push_back((neighbor, current));
+                    // Light sources stay lit
+                    let emission = bloc_type_at(game_state, neighbor).map_or(0, |t| t.light_emission());
+                    if channel == LightChannel::Bloc && emission > 0 {
+                        self.set(channel, neighbor, emission);
+                        relight.push_back(neighbor);
+                    }
+                } else {
+                    relight.push_back(neighbor);
+                }
+            }
+        }
+        relight
+    }
+
+    pub fn add_chunk(&mut self, game_state: &GameState, chunk_pos: ChunkPos) {
+        let types = match game_state.chunks.get(&chunk_pos) {
+            Some(types) => types,
+            None => return
+        };
+        self.chunks.insert(chunk_pos, ChunkLight::default());
+        let origin = bloc_coordinates(chunk_pos, PosInChunk { x: 0, y: 0, z: 0 });
+
+        let mut sky = VecDeque::new();
+        let mut bloc = VecDeque::new();
+        for x in 0..CHUNK_X as u8 {
+            for z in 0..CHUNK_Z as u8 {
+                // The sky lights the column down to the first opaque bloc
+                let mut open = true;
+                for y in (0..CHUNK_Y as u8).rev() {
+                    let pos = PosInChunk { x, y, z };
+                    let r#type = types.0[pos.to_chunk_index()];
+                    let coordinates = bloc_coordinates(chunk_pos, pos);
+                    open &= !r#type.is_opaque();
+                    if open {
+                        self.set(LightChannel::Sky, coordinates, MAX_LIGHT);
+                        sky.push_back(coordinates);
+                    }
+                    if r#type.light_emission() > 0 {
+                        self.set(LightChannel::Bloc, coordinates, r#type.light_emission());
+                        bloc.push_back(coordinates);
+                    }
+                }
+            }
+        }
+        // Borders of the neighbor chunks
+        let mut borders = Vec::new();
+        for y in 0..CHUNK_Y as i32 {
+            for z in 0..CHUNK_Z as i32 {
+                borders.push(origin + IVec3::new(-1, y, z));
+                borders.push(origin + IVec3::new(CHUNK_X as i32, y, z));
+            }
+            for x in 0..CHUNK_X as i32 {
+                borders.push(origin + IVec3::new(x, y, -1));
+                borders.push(origin + IVec3::new(x, y, CHUNK_Z as i32));
+            }
+        }
+        for border in borders {
+            if self.try_get(LightChannel::Sky, border).is_some() {
+                sky.push_back(border);
+                bloc.push_back(border);
+            }
+        }
+        self.propagate(game_state, LightChannel::Sky, sky);
+        self.propagate(game_state, LightChannel::Bloc, bloc);
+    }
+
+    pub fn remove_chunk(&mut self, chunk_pos: ChunkPos) {
+        self.chunks.remove(&chunk_pos);
+    }
+
+    pub fn update_bloc(&mut self, game_state: &GameState, bloc: IVec3) {
+        let r#type = match bloc_type_at(game_state, bloc) {
+            Some(r#type) => r#type,
+            None => return
+        };
+        for channel in [LightChannel::Sky, LightChannel::Bloc] {
+            let mut relight = self.remove(game_state, channel, bloc);
+            match channel {
+                LightChannel::Bloc if r#type.light_emission() > 0 => {
+                    self.set(channel, bloc, r#type.light_emission());
+                    relight.push_back(bloc);
+                },
+                LightChannel::Sky if !r#type.is_opaque() && bloc.y == CHUNK_Y as i32 - 1 => {
+                    self.set(channel, bloc, MAX_LIGHT);
+                    relight.push_back(bloc);
+                },
+                _ => {}
+            }
+            // The neighbors light the bloc if it isn't opaque anymore
+            relight.extend(NEIGHBORS.map(|dir| bloc + dir));
+            self.propagate(game_state, channel, relight);
+        }
+    }
+}
+
+pub fn update_light(
+    mut light: ResMut<LightMap>,
+    game_state: Res<GameState>,
+    changed_blocs: Query<(&PosInChunk, &Parent, Ref<BlocType>), Changed<BlocType>>,
+    chunk_pos_query: Query<&ChunkPos>,
+    mut ev_render: EventReader<Render>
+) {
+    // A loaded save refills the chunks under the same positions, all their light must be computed again
+    let reload = ev_render.read().count() > 0;
+    let removed: Vec<ChunkPos> = light.chunks.keys().filter(|pos| reload || !game_state.chunks.contains_key(*pos)).copied().collect();
+    for chunk_pos in removed {
+        light.remove_chunk(chunk_pos);
+    }
+    let added: Vec<ChunkPos> = game_state.chunks.keys().filter(|pos| !light.chunks.contains_key(*pos)).copied().collect();
+    for chunk_pos in added {
+        light.add_chunk(&game_state, chunk_pos);
+    }
+
+    for (pos, parent, bloc_type) in changed_blocs.iter() {
+        // New blocs are lit with their chunk
+        if bloc_type.is_added() {
+            continue
+        }
+        if let Ok(chunk_pos) = chunk_pos_query.get(parent.get()) {
+            light.update_bloc(&game_state, bloc_coordinates(*chunk_pos, *pos));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkTypes;
+
+    const ROOF: u8 = 10;
+
+    // Stone roof from `ROOF` to the top of the world
+    fn roofed_chunk() -> GameState {
+        let mut types = [BlocType::Air; CHUNK_X*CHUNK_Y*CHUNK_Z];
+        for (i, r#type) in types.iter_mut().enumerate() {
+            if PosInChunk::from_chunk_index(i).y >= ROOF {
+                *r#type = BlocType::Stone;
+            }
+        }
+        let mut game_state = GameState::default();
+        game_state.chunks.insert(ChunkPos { x: 0, y: 0, z: 0 }, ChunkTypes(types));
+        game_state
+    }
+
+    fn set_bloc(game_state: &mut GameState, bloc: IVec3, r#type: BlocType) {
+        let (chunk_pos, pos) = split_bloc_coordinates(bloc).unwrap();
+        game_state.chunks.get_mut(&chunk_pos).unwrap().0[pos.to_chunk_index()] = r#type;
+    }
+
+    #[test]
+    fn sky_lights_an_open_chunk() {
+        let mut game_state = GameState::default();
+        game_state.chunks.insert(ChunkPos { x: 0, y: 0, z: 0 }, ChunkTypes([BlocType::Air; CHUNK_X*CHUNK_Y*CHUNK_Z]));
+        let mut light = LightMap::default();
+        light.add_chunk(&game_state, ChunkPos { x: 0, y: 0, z: 0 });
+        for i in 0..CHUNK_X*CHUNK_Y*CHUNK_Z {
+            let bloc = bloc_coordinates(ChunkPos { x: 0, y: 0, z: 0 }, PosInChunk::from_chunk_index(i));
+            assert_eq!(light.get(LightChannel::Sky, bloc), MAX_LIGHT);
+            assert_eq!(light.get(LightChannel::Bloc, bloc), 0);
+        }
+    }
+
+    #[test]
+    fn bloc_light_fades_with_distance_and_is_removed() {
+        let mut game_state = roofed_chunk();
+        let glowstone = IVec3::new(4, 5, 4);
+        set_bloc(&mut game_state, glowstone, BlocType::Glowstone);
+        let mut light = LightMap::default();
+        light.add_chunk(&game_state, ChunkPos { x: 0, y: 0, z: 0 });
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(5, 5, 4)), 0);
+        assert_eq!(light.get(LightChannel::Bloc, glowstone), MAX_LIGHT);
+        assert_eq!(light.get(LightChannel::Bloc, IVec3::new(5, 5, 4)), MAX_LIGHT - 1);
+        assert_eq!(light.get(LightChannel::Bloc, IVec3::new(4, 5, 7)), MAX_LIGHT - 3);
+        assert_eq!(light.get(LightChannel::Bloc, IVec3::new(0, 0, 0)), MAX_LIGHT - 13);
+        // The roof is opaque
+        assert_eq!(light.get(LightChannel::Bloc, IVec3::new(4, ROOF as i32, 4)), 0);
+
+        set_bloc(&mut game_state, glowstone, BlocType::Air);
+        light.update_bloc(&game_state, glowstone);
+        for i in 0..CHUNK_X*CHUNK_Y*CHUNK_Z {
+            let bloc = bloc_coordinates(ChunkPos { x: 0, y: 0, z: 0 }, PosInChunk::from_chunk_index(i));
+            assert_eq!(light.get(LightChannel::Bloc, bloc), 0);
+        }
+    }
+
+    #[test]
+    fn sky_light_goes_down_an_opened_roof() {
+        let mut game_state = roofed_chunk();
+        let mut light = LightMap::default();
+        light.add_chunk(&game_state, ChunkPos { x: 0, y: 0, z: 0 });
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(4, 3, 4)), 0);
+
+        for y in ROOF as i32..CHUNK_Y as i32 {
+            set_bloc(&mut game_state, IVec3::new(4, y, 4), BlocType::Air);
+        }
+        for y in (ROOF as i32..CHUNK_Y as i32).rev() {
+            light.update_bloc(&game_state, IVec3::new(4, y, 4));
+        }
+        for y in 0..CHUNK_Y as i32 {
+            assert_eq!(light.get(LightChannel::Sky, IVec3::new(4, y, 4)), MAX_LIGHT);
+        }
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(5, 3, 4)), MAX_LIGHT - 1);
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(4, 3, 6)), MAX_LIGHT - 2);
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(5, ROOF as i32, 4)), 0);
+
+        // Closing the roof again darkens the chunk
+        set_bloc(&mut game_state, IVec3::new(4, CHUNK_Y as i32 - 1, 4), BlocType::Stone);
+        light.update_bloc(&game_state, IVec3::new(4, CHUNK_Y as i32 - 1, 4));
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(4, 3, 4)), 0);
+        assert_eq!(light.get(LightChannel::Sky, IVec3::new(5, 3, 4)), 0);
+    }
+}
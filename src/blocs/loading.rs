@@ -2,8 +2,8 @@ use bevy::prelude::*;
 use crate::{blocs::*, PlayerMarker};
 
 // Do not put a value higher than 2^31 (with margin)
-const RENDER_DISTANCE: u32 = 5;
-const PHYSIC_DISTANCE: u32 = 2;
+pub const RENDER_DISTANCE: u32 = 5;
+pub const PHYSIC_DISTANCE: u32 = 2;
 
 pub struct LoadingPlugin;
 impl Plugin for LoadingPlugin {
@@ -25,11 +25,7 @@ pub fn load_chunks(
     mut cmds: Commands
 ) {
     let player_pos = player.single();
-    let player_chunk = ChunkPos {
-        x: (player_pos.translation.x / (CHUNK_X as f32*SQUARE_UNIT)).round() as i32,
-        y: (player_pos.translation.y / (CHUNK_Y as f32*SQUARE_UNIT)).round() as i32,
-        z: (player_pos.translation.z / (CHUNK_Z as f32*SQUARE_UNIT)).round() as i32
-    };
+    let player_chunk = ChunkPos::from_translation(player_pos.translation);
     for x in -(RENDER_DISTANCE as i32)+player_chunk.x..(RENDER_DISTANCE as i32)+player_chunk.x {
         for z in -(RENDER_DISTANCE as i32)+player_chunk.z..(RENDER_DISTANCE as i32)+player_chunk.z {
             if (x - player_chunk.x).saturating_pow(2) as u32 + (z - player_chunk.z).saturating_pow(2) as u32 > RENDER_DISTANCE.pow(2) {
@@ -44,24 +40,34 @@ pub fn load_chunks(
     }
 }
 
+// Chunks are only looked at when the player changes chunk, or when they are added or changed
 pub fn render_physic(
-    chunks_query: Query<(&ChunkBlocs, &ChunkPos)>,
+    mut chunks_query: Query<(Entity, &ChunkBlocs, &ChunkPos, &mut ChunkPhysic)>,
     player: Query<&Transform, With<PlayerMarker>>,
-    blocs_query: Query<(Entity, &Neighbors)>,
+    mut last_player_chunk: Local<Option<ChunkPos>>,
     mut cmds: Commands,
     blocs_types_query: Query<&BlocType>
 ) {
     let player_pos = player.single();
-    let player_chunk = ChunkPos {
-        x: (player_pos.translation.x / (CHUNK_X as f32*SQUARE_UNIT)).round() as i32,
-        y: (player_pos.translation.y / (CHUNK_Y as f32*SQUARE_UNIT)).round() as i32,
-        z: (player_pos.translation.z / (CHUNK_Z as f32*SQUARE_UNIT)).round() as i32
-    };
-    for (blocs, pos) in chunks_query.iter() {
-        if ((pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32) < PHYSIC_DISTANCE.pow(2) {
-            blocs.load_physic(&blocs_query, &blocs_types_query, &mut cmds);
-        } else {
-            blocs.unload_physic(&mut cmds);
+    let player_chunk = ChunkPos::from_translation(player_pos.translation);
+    let player_moved = *last_player_chunk != Some(player_chunk);
+    *last_player_chunk = Some(player_chunk);
+
+    for (chunk, blocs, pos, mut physic) in chunks_query.iter_mut() {
+        if !player_moved && !physic.is_changed() {
+            continue
+        }
+        let in_range = ((pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32) < PHYSIC_DISTANCE.pow(2);
+        match (*physic, in_range) {
+            (ChunkPhysic::Loaded, true) | (ChunkPhysic::Unloaded, false) => {},
+            (_, true) => {
+                blocs.load_physic(chunk, &blocs_types_query, &mut cmds);
+                *physic = ChunkPhysic::Loaded;
+            },
+            (_, false) => {
+                blocs.unload_physic(chunk, &mut cmds);
+                *physic = ChunkPhysic::Unloaded;
+            }
         }
     }
 }
@@ -74,11 +80,7 @@ pub fn unload_chunks(
     mut cmds: Commands
 ) {
     let player_pos = player.single();
-    let player_chunk = ChunkPos {
-        x: (player_pos.translation.x / (CHUNK_X as f32*SQUARE_UNIT)).round() as i32,
-        y: (player_pos.translation.y / (CHUNK_Y as f32*SQUARE_UNIT)).round() as i32,
-        z: (player_pos.translation.z / (CHUNK_Z as f32*SQUARE_UNIT)).round() as i32
-    };
+    let player_chunk = ChunkPos::from_translation(player_pos.translation);
     for pos in chunks.inner.keys().map(|x|*x).collect::<Vec<_>>() {
         if (pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32 > RENDER_DISTANCE.pow(2) {
             chunks.unload(pos, &mut chunks_query, &mut blocs_query, &mut cmds);
@@ -90,23 +92,17 @@ pub fn render_added_chunks(
     chunks_query: Query<(&ChunkBlocs, &ChunkPos), Added<ChunkBlocs>>,
     player: Query<&Transform, With<PlayerMarker>>,
     mut cmds: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    bloc_assets: Res<BlocAssets>,
     blocs_types_query: Query<&BlocType>,
     mut blocs_query: Query<(Entity, &Neighbors, &mut BlocFaces)>
 ) {
     let player_pos = player.single();
-    let player_chunk = ChunkPos {
-        x: (player_pos.translation.x / (CHUNK_X as f32*SQUARE_UNIT)).round() as i32,
-        y: (player_pos.translation.y / (CHUNK_Y as f32*SQUARE_UNIT)).round() as i32,
-        z: (player_pos.translation.z / (CHUNK_Z as f32*SQUARE_UNIT)).round() as i32
-    };
+    let player_chunk = ChunkPos::from_translation(player_pos.translation);
     for (blocs, pos) in chunks_query.iter() {
         if (pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32 > RENDER_DISTANCE.pow(2) {
             continue
         }
-        blocs.render(&asset_server, &mut blocs_query, &blocs_types_query, &mut meshes, &mut materials, &mut cmds, Some(((pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32) < PHYSIC_DISTANCE.pow(2)));
+        blocs.render(&mut blocs_query, &blocs_types_query, &bloc_assets, &mut cmds);
     }
 }
 
@@ -115,26 +111,16 @@ pub struct Render;
 
 fn render_all(
     mut ev_render: EventReader<Render>,
-    mut chunks_query: Query<(&ChunkBlocs, &ChunkPos)>,
+    chunks_query: Query<&ChunkBlocs>,
     mut cmds: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    bloc_assets: Res<BlocAssets>,
     blocs_types_query: Query<&BlocType>,
-    mut blocs_query: Query<(Entity, &Neighbors, &mut BlocFaces)>,
-    player: Query<&Transform, With<PlayerMarker>>
+    mut blocs_query: Query<(Entity, &Neighbors, &mut BlocFaces)>
 ) {
     if ev_render.read().count() > 0 {
         dbg!("render");
-        let player_pos = player.single();
-        let player_chunk = ChunkPos {
-            x: (player_pos.translation.x / (CHUNK_X as f32*SQUARE_UNIT)).round() as i32,
-            y: (player_pos.translation.y / (CHUNK_Y as f32*SQUARE_UNIT)).round() as i32,
-            z: (player_pos.translation.z / (CHUNK_Z as f32*SQUARE_UNIT)).round() as i32
-        };
-        for (blocs, pos) in chunks_query.iter_mut() {
-            let need_physic = ((pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32) < PHYSIC_DISTANCE.pow(2);
-            blocs.render(&asset_server, &mut blocs_query, &blocs_types_query, &mut meshes, &mut materials, &mut cmds, Some(need_physic));
+        for blocs in chunks_query.iter() {
+            blocs.render(&mut blocs_query, &blocs_types_query, &bloc_assets, &mut cmds);
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,154 @@
+use bevy::{prelude::*, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}, utils::HashMap};
+use crate::{blocs::*, ChunkSaves, GameState, PlayerMarker};
+
+// Chunks between `RENDER_DISTANCE` and this distance are drawn without bloc entities nor physic
+pub const LOD_DISTANCE: u32 = 16;
+const LOD_BUILDS_PER_FRAME: usize = 4;
+
+pub struct LodPlugin;
+impl Plugin for LodPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LodChunks>()
+            .add_systems(Startup, setup_lod_material)
+            .add_systems(Update, update_lod_chunks);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LodChunks {
+    inner: HashMap<ChunkPos, Entity>,
+    // Colors are in the vertices
+    material: Handle<StandardMaterial>
+}
+
+#[derive(Component)]
+pub struct LodChunk;
+
+pub fn setup_lod_material(
+    mut lod_chunks: ResMut<LodChunks>,
+    mut materials: ResMut<Assets<StandardMaterial>>
+) {
+    lod_chunks.material = materials.add(StandardMaterial::default());
+}
+
+#[derive(Default)]
+struct LodMeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>
+}
+impl LodMeshBuilder {
+    fn quad(&mut self, center: Vec3, normal: Vec3, up: Vec3, size: Vec2, color: Color) {
+        let right = up.cross(normal)*size.x/2.0;
+        let up = up*size.y/2.0;
+        let first = self.positions.len() as u32;
+        for corner in [center - right - up, center + right - up, center + right + up, center - right + up] {
+            self.positions.push(corner.into());
+            self.normals.push(normal.into());
+            self.colors.push(color.as_linear_rgba_f32());
+        }
+        self.indices.extend([0, 1, 2, 0, 2, 3].map(|i| first + i));
+    }
+    fn build(self) -> Option<Mesh> {
+        if self.indices.is_empty() {
+            return None
+        }
+        Some(Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_indices(Indices::U32(self.indices))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, self.colors))
+    }
+}
+
+// Columns are walled down to their lower neighbors, so there are no holes between two LOD chunks
+pub fn lod_mesh(types: &[BlocType; CHUNK_X*CHUNK_Y*CHUNK_Z]) -> Option<Mesh> {
+    let mut tops = [[None; CHUNK_Z]; CHUNK_X];
+    for x in 0..CHUNK_X {
+        for z in 0..CHUNK_Z {
+            tops[x][z] = (0..CHUNK_Y as u8).rev()
+                .map(|y| (y, types[PosInChunk { x: x as u8, y, z: z as u8 }.to_chunk_index()]))
+                .find(|(_, r#type)| r#type.rendering() != BlocRendering::Invisible);
+        }
+    }
+    // Height of the top surface of a column, the bottom of the world if it is empty
+    let surface = |top: Option<(u8, BlocType)>| top.map_or(-0.5, |(y, _)| y as f32 + 0.5)*SQUARE_UNIT;
+
+    let mut builder = LodMeshBuilder::default();
+    for x in 0..CHUNK_X {
+        for z in 0..CHUNK_Z {
+            let (_, r#type) = match tops[x][z] {
+                Some(top) => top,
+                None => continue
+            };
+            let top = surface(tops[x][z]);
+            let column = Vec3::new(x as f32, 0.0, z as f32)*SQUARE_UNIT;
+            builder.quad(Vec3::new(column.x, top, column.z), Vec3::Y, Vec3::NEG_Z, Vec2::splat(SQUARE_UNIT), r#type.color());
+            for normal in [IVec3::X, IVec3::NEG_X, IVec3::Z, IVec3::NEG_Z] {
+                let (nx, nz) = (x as i32 + normal.x, z as i32 + normal.z);
+                let bottom = if (0..CHUNK_X as i32).contains(&nx) && (0..CHUNK_Z as i32).contains(&nz) {
+                    surface(tops[nx as usize][nz as usize])
+                } else {
+                    surface(None)
+                };
+                if bottom >= top {
+                    continue
+                }
+                let normal = normal.as_vec3();
+                let center = column + normal*(SQUARE_UNIT/2.0);
+                builder.quad(Vec3::new(center.x, (top + bottom)/2.0, center.z), normal, Vec3::Y, Vec2::new(SQUARE_UNIT, top - bottom), r#type.color());
+            }
+        }
+    }
+    builder.build()
+}
+
+// A LOD chunk is kept until its full chunk is loaded, so there is no hole while the player moves
+pub fn update_lod_chunks(
+    player: Query<&Transform, With<PlayerMarker>>,
+    chunks: Res<Chunks<DefaultGenerator>>,
+    (game_state, chunk_saves): (Res<GameState>, Res<ChunkSaves>),
+    mut lod_chunks: ResMut<LodChunks>,
+    mut ev_render: EventReader<Render>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut cmds: Commands
+) {
+    let player_pos = player.single();
+    let player_chunk = ChunkPos::from_translation(player_pos.translation);
+    let distance = |pos: &ChunkPos| (pos.x - player_chunk.x).saturating_pow(2) as u32 + (pos.z - player_chunk.z).saturating_pow(2) as u32;
+
+    // A loaded save can change any chunk
+    let reload = ev_render.read().count() > 0;
+    lod_chunks.inner.retain(|pos, entity| {
+        let keep = !reload && distance(pos) <= LOD_DISTANCE.pow(2) && chunks.get(*pos).is_none();
+        if !keep {
+            cmds.entity(*entity).despawn_recursive();
+        }
+        keep
+    });
+
+    let mut missing = Vec::new();
+    for x in -(LOD_DISTANCE as i32)+player_chunk.x..=(LOD_DISTANCE as i32)+player_chunk.x {
+        for z in -(LOD_DISTANCE as i32)+player_chunk.z..=(LOD_DISTANCE as i32)+player_chunk.z {
+            let pos = ChunkPos { x, y: 0, z };
+            let d = distance(&pos);
+            if d > RENDER_DISTANCE.pow(2) && d <= LOD_DISTANCE.pow(2) && !lod_chunks.inner.contains_key(&pos) && chunks.get(pos).is_none() {
+                missing.push(pos);
+            }
+        }
+    }
+    missing.sort_unstable_by_key(distance);
+
+    for pos in missing.into_iter().take(LOD_BUILDS_PER_FRAME) {
+        let types = match game_state.chunks.get(&pos) {
+            Some(types) => types.0,
+            None => chunks.generate_types(pos, &chunk_saves)
+        };
+        let mut cmd = cmds.spawn((SpatialBundle::from_transform(pos.into()), LodChunk));
+        if let Some(mesh) = lod_mesh(&types) {
+            cmd.insert((meshes.add(mesh), lod_chunks.material.clone()));
+        }
+        lod_chunks.inner.insert(pos, cmd.id());
+    }
+}
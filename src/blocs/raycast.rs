@@ -0,0 +1,185 @@
+use bevy::prelude::*;
+use super::{BlocType, ChunkPos, Direction, PosInChunk, CHUNK_X, CHUNK_Y, CHUNK_Z, SQUARE_UNIT};
+use crate::GameState;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BlocHit {
+    pub chunk_pos: ChunkPos,
+    pub pos: PosInChunk,
+    pub bloc: BlocType,
+    // Face the ray entered by
+    pub face: Direction,
+    pub distance: f32
+}
+impl BlocHit {
+    pub fn translation(&self) -> Vec3 {
+        self.pos.to_translation(self.chunk_pos)
+    }
+}
+
+// `None` above or under the world
+pub fn split_bloc_coordinates(bloc: IVec3) -> Option<(ChunkPos, PosInChunk)> {
+    if bloc.y < 0 || bloc.y >= CHUNK_Y as i32 {
+        return None
+    }
+    Some((
+        ChunkPos {
+            x: bloc.x.div_euclid(CHUNK_X as i32),
+            y: 0,
+            z: bloc.z.div_euclid(CHUNK_Z as i32)
+        },
+        PosInChunk {
+            x: bloc.x.rem_euclid(CHUNK_X as i32) as u8,
+            y: bloc.y as u8,
+            z: bloc.z.rem_euclid(CHUNK_Z as i32) as u8
+        }
+    ))
+}
+
+pub fn bloc_coordinates(chunk_pos: ChunkPos, pos: PosInChunk) -> IVec3 {
+    IVec3::new(
+        chunk_pos.x*CHUNK_X as i32 + pos.x as i32,
+        pos.y as i32,
+        chunk_pos.z*CHUNK_Z as i32 + pos.z as i32
+    )
+}
+
+// `None` outside of the world or if its chunk doesn't exist
+pub fn bloc_type_at(game_state: &GameState, bloc: IVec3) -> Option<BlocType> {
+    let (chunk_pos, pos) = split_bloc_coordinates(bloc)?;
+    game_state.chunks.get(&chunk_pos).map(|types| types.0[pos.to_chunk_index()])
+}
+
+// Walks the bloc grid (Amanatides & Woo). The bloc containing `origin` is ignored
+pub fn raycast(game_state: &GameState, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<BlocHit> {
+    let dir = dir.normalize_or_zero();
+    if dir == Vec3::ZERO {
+        return None
+    }
+    // In bloc units, with the bloc borders on integers
+    let start = (origin / SQUARE_UNIT) + Vec3::splat(0.5);
+    let max_distance = max_distance / SQUARE_UNIT;
+    let mut cell = start.floor().as_ivec3();
+    let step = IVec3::new(dir.x.signum() as i32, dir.y.signum() as i32, dir.z.signum() as i32);
+
+    // Distance along the ray to cross one bloc on each axis
+    let mut t_delta = Vec3::splat(f32::INFINITY);
+    // Distance along the ray to the next bloc border on each axis
+    let mut t_max = Vec3::splat(f32::INFINITY);
+    for axis in 0..3 {
+        if dir[axis] > 0.0 {
+            t_delta[axis] = 1.0 / dir[axis];
+            t_max[axis] = (cell[axis] as f32 + 1.0 - start[axis]) * t_delta[axis];
+        } else if dir[axis] < 0.0 {
+            t_delta[axis] = -1.0 / dir[axis];
+            t_max[axis] = (start[axis] - cell[axis] as f32) * t_delta[axis];
+        }
+    }
+
+    loop {
+        let axis = if t_max.x <= t_max.y && t_max.x <= t_max.z {
+            0
+        } else if t_max.y <= t_max.z {
+            1
+        } else {
+            2
+        };
+        let distance = t_max[axis];
+        if distance > max_distance {
+            return None
+        }
+        cell[axis] += step[axis];
+        t_max[axis] += t_delta[axis];
+
+        let bloc = match bloc_type_at(game_state, cell) {
+            None | Some(BlocType::Air) => continue,
+            Some(bloc) => bloc
+        };
+        let (chunk_pos, pos) = split_bloc_coordinates(cell)?;
+        let face = match (axis, step[axis] > 0) {
+            (0, true) => Direction::Left,
+            (0, false) => Direction::Right,
+            (1, true) => Direction::Down,
+            (1, false) => Direction::Up,
+            (_, true) => Direction::Back,
+            (_, false) => Direction::Front
+        };
+        return Some(BlocHit {
+            chunk_pos,
+            pos,
+            bloc,
+            face,
+            distance: distance * SQUARE_UNIT
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkTypes;
+
+    // Empty chunks with stone at these absolute bloc coordinates
+    fn world(stones: &[IVec3]) -> GameState {
+        let mut game_state = GameState::default();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                game_state.chunks.insert(ChunkPos { x, y: 0, z }, ChunkTypes([BlocType::Air; CHUNK_X*CHUNK_Y*CHUNK_Z]));
+            }
+        }
+        for stone in stones {
+            let (chunk_pos, pos) = split_bloc_coordinates(*stone).unwrap();
+            game_state.chunks.get_mut(&chunk_pos).unwrap().0[pos.to_chunk_index()] = BlocType::Stone;
+        }
+        game_state
+    }
+
+    #[test]
+    fn bloc_coordinates_round_trip() {
+        for bloc in [IVec3::new(0, 0, 0), IVec3::new(-1, 5, -9), IVec3::new(17, 15, -8)] {
+            let (chunk_pos, pos) = split_bloc_coordinates(bloc).unwrap();
+            assert_eq!(bloc_coordinates(chunk_pos, pos), bloc);
+        }
+        assert!(split_bloc_coordinates(IVec3::new(0, -1, 0)).is_none());
+        assert!(split_bloc_coordinates(IVec3::new(0, CHUNK_Y as i32, 0)).is_none());
+    }
+
+    #[test]
+    fn raycast_hits_the_first_bloc() {
+        let game_state = world(&[IVec3::new(3, 2, 0), IVec3::new(5, 2, 0)]);
+        let hit = raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::X, 10.0).unwrap();
+        assert_eq!(hit.translation(), Vec3::new(3.0, 2.0, 0.0));
+        assert_eq!(hit.bloc, BlocType::Stone);
+        assert_eq!(hit.face, Direction::Left);
+        assert!((hit.distance - 2.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn raycast_crosses_chunks() {
+        let game_state = world(&[IVec3::new(-6, 4, -3)]);
+        let hit = raycast(&game_state, Vec3::new(-6.0, 8.0, -3.0), Vec3::NEG_Y, 10.0).unwrap();
+        assert_eq!(hit.chunk_pos, ChunkPos { x: -1, y: 0, z: -1 });
+        assert_eq!(hit.translation(), Vec3::new(-6.0, 4.0, -3.0));
+        assert_eq!(hit.face, Direction::Up);
+
+        let game_state = world(&[IVec3::new(9, 3, 2)]);
+        let hit = raycast(&game_state, Vec3::new(6.0, 3.0, 2.0), Vec3::X, 10.0).unwrap();
+        assert_eq!(hit.chunk_pos, ChunkPos { x: 1, y: 0, z: 0 });
+        assert_eq!(hit.pos, PosInChunk { x: 1, y: 3, z: 2 });
+    }
+
+    #[test]
+    fn raycast_stops_at_max_distance() {
+        let game_state = world(&[IVec3::new(0, 2, 4)]);
+        assert!(raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::Z, 3.0).is_none());
+        assert!(raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::Z, 4.0).is_some());
+        assert!(raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::NEG_Z, 10.0).is_none());
+        assert!(raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::ZERO, 10.0).is_none());
+    }
+
+    #[test]
+    fn raycast_ignores_the_origin_bloc() {
+        let game_state = world(&[IVec3::new(0, 2, 0)]);
+        assert!(raycast(&game_state, Vec3::new(0.0, 2.0, 0.0), Vec3::Y, 10.0).is_none());
+    }
+}
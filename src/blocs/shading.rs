@@ -0,0 +1,131 @@
+use bevy::{prelude::*, render::{mesh::{Indices, PrimitiveTopology}, render_asset::RenderAssetUsages}, utils::HashMap};
+use crate::{blocs::*, GameState};
+
+// So unlit caves aren't pitch black
+const MIN_BRIGHTNESS: f32 = 0.04;
+const LIGHT_FALLOFF: f32 = 0.8;
+
+pub struct ShadingPlugin;
+impl Plugin for ShadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShadedQuads>()
+            .add_systems(PostUpdate, bake_face_shading);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaceShading {
+    pub ao: [u8; 4],
+    pub light: u8
+}
+
+pub fn light_brightness(light: u8) -> f32 {
+    MIN_BRIGHTNESS + (1.0 - MIN_BRIGHTNESS)*LIGHT_FALLOFF.powi((MAX_LIGHT - light.min(MAX_LIGHT)) as i32)
+}
+
+#[derive(Resource, Default)]
+pub struct ShadedQuads(HashMap<FaceShading, Handle<Mesh>>);
+impl ShadedQuads {
+    pub fn get(&mut self, shading: FaceShading, meshes: &mut Assets<Mesh>) -> Handle<Mesh> {
+        self.0.entry(shading).or_insert_with(|| meshes.add(shaded_quad(shading))).clone()
+    }
+}
+
+// Same vertices as `Rectangle::new(SQUARE_UNIT, SQUARE_UNIT)`
+fn shaded_quad(shading: FaceShading) -> Mesh {
+    let h = SQUARE_UNIT/2.0;
+    let ao = shading.ao;
+    let light = light_brightness(shading.light);
+    let colors = ao.map(|level| {
+        let b = AO_BRIGHTNESS[level as usize]*light;
+        [b, b, b, 1.0]
+    });
+    // Split the quad along its brightest diagonal, otherwise the occlusion is interpolated unevenly
+    let indices = if ao[0] as u16 + ao[2] as u16 >= ao[1] as u16 + ao[3] as u16 {
+        vec![0, 1, 2, 0, 2, 3]
+    } else {
+        vec![1, 2, 3, 1, 3, 0]
+    };
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_indices(Indices::U32(indices))
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, QUAD_CORNERS.map(|[x, y]| [x*h, y*h, 0.0]).to_vec())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 4])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[1.0, 0.0], [0.0, 0.0], [0.0, 1.0], [1.0, 1.0]])
+        .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors.to_vec())
+}
+
+fn bloc_entity_coordinates(bloc: Entity, blocs: &Query<(&PosInChunk, &Parent, &BlocFaces)>, chunk_pos_query: &Query<&ChunkPos>) -> Option<IVec3> {
+    let (pos, parent, _) = blocs.get(bloc).ok()?;
+    let chunk_pos = chunk_pos_query.get(parent.get()).ok()?;
+    Some(bloc_coordinates(*chunk_pos, *pos))
+}
+
+fn faces_of(coordinates: impl IntoIterator<Item = IVec3>, blocs: &Query<(&PosInChunk, &Parent, &BlocFaces)>, chunks: &Chunks<DefaultGenerator>, chunks_query: &Query<&ChunkBlocs>, faces: &mut Vec<Entity>) {
+    for bloc in coordinates {
+        let (chunk_pos, pos) = match split_bloc_coordinates(bloc) {
+            Some(coordinates) => coordinates,
+            None => continue
+        };
+        let bloc = match chunks.get_bloc(chunk_pos, &pos, chunks_query) {
+            Some(bloc) => bloc,
+            None => continue
+        };
+        if let Ok((_, _, bloc_faces)) = blocs.get(bloc) {
+            faces.extend(bloc_faces.0.iter().copied());
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn bake_face_shading(
+    mut faces: Query<(&FaceMarker, &Transform, &Parent, &mut Handle<Mesh>)>,
+    new_faces: Query<Entity, Added<FaceMarker>>,
+    changed_blocs: Query<(&PosInChunk, &Parent, Ref<BlocType>), Changed<BlocType>>,
+    blocs: Query<(&PosInChunk, &Parent, &BlocFaces)>,
+    (chunk_pos_query, chunks_query): (Query<&ChunkPos>, Query<&ChunkBlocs>),
+    chunks: Res<Chunks<DefaultGenerator>>,
+    (game_state, mut light): (Res<GameState>, ResMut<LightMap>),
+    mut shaded_quads: ResMut<ShadedQuads>,
+    mut meshes: ResMut<Assets<Mesh>>
+) {
+    let mut to_bake: Vec<Entity> = new_faces.iter().collect();
+
+    // A bloc changes the occlusion of the faces of the blocs around it, not only of its direct neighbors
+    for (pos, parent, bloc_type) in changed_blocs.iter() {
+        // New blocs don't have faces yet, they get their shading with their new faces
+        if bloc_type.is_added() {
+            continue
+        }
+        if let Ok(chunk_pos) = chunk_pos_query.get(parent.get()) {
+            let center = bloc_coordinates(*chunk_pos, *pos);
+            let around = (-1..=1).flat_map(|x| (-1..=1).flat_map(move |y| (-1..=1).map(move |z| center + IVec3::new(x, y, z))));
+            faces_of(around, &blocs, &chunks, &chunks_query, &mut to_bake);
+        }
+    }
+    // The light of a bloc is the light of the faces of its neighbors
+    let changed: Vec<IVec3> = light.changed.drain().collect();
+    for bloc in changed {
+        faces_of(NEIGHBORS.map(|dir| bloc + dir), &blocs, &chunks, &chunks_query, &mut to_bake);
+    }
+    to_bake.sort_unstable();
+    to_bake.dedup();
+
+    for face in to_bake {
+        let (marker, transform, parent, mut mesh) = match faces.get_mut(face) {
+            Ok(face) => face,
+            Err(_) => continue
+        };
+        let bloc = match bloc_entity_coordinates(parent.get(), &blocs, &chunk_pos_query) {
+            Some(bloc) => bloc,
+            None => continue
+        };
+        let shading = FaceShading {
+            ao: face_ambient_occlusion(&game_state, bloc, marker.0, transform),
+            light: light.level(bloc + marker.0.normal().round().as_ivec3())
+        };
+        let quad = shaded_quads.get(shading, &mut meshes);
+        if *mesh != quad {
+            *mesh = quad;
+        }
+    }
+}
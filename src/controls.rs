@@ -0,0 +1,357 @@
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Serialize, Deserialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+
+const CONFIG_PATH: &str = "controls.ron";
+
+pub struct ControlsPlugin;
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMap::load())
+            .add_systems(Update, save_input_map);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Forward,
+    Backward,
+    Right,
+    Left,
+    Jump,
+    Run,
+    /// Go down while flying
+    Down,
+    /// Crouch, the player can't fall from bloc edges while crouching
+    Crouch,
+    Noclip,
+    ToggleAutoJump,
+    Break,
+    Place,
+    PickBloc,
+    /// Select a hotbar slot (from 0 to `HOTBAR_SIZE`-1)
+    Hotbar(u8),
+    SwitchGameMode,
+    SkipTime,
+    TimeSpeed,
+    /// Respawn at the current position after dying
+    SetSpawnPoint,
+    Save,
+    Load,
+    ReleaseCursor
+}
+impl Action {
+    pub fn list() -> Vec<Action> {
+        let mut list = vec![
+            Action::Forward,
+            Action::Backward,
+            Action::Right,
+            Action::Left,
+            Action::Jump,
+            Action::Run,
+            Action::Down,
+            Action::Crouch,
+            Action::Noclip,
+            Action::ToggleAutoJump,
+            Action::Break,
+            Action::Place,
+            Action::PickBloc,
+            Action::SwitchGameMode,
+            Action::SkipTime,
+            Action::TimeSpeed,
+            Action::SetSpawnPoint,
+            Action::Save,
+            Action::Load,
+            Action::ReleaseCursor
+        ];
+        list.extend((0..crate::HOTBAR_SIZE as u8).map(Action::Hotbar));
+        list
+    }
+    pub fn default_gamepad_binding(&self) -> Option<GamepadButtonType> {
+        match self {
+            Action::Jump => Some(GamepadButtonType::South),
+            Action::Run => Some(GamepadButtonType::LeftThumb),
+            Action::Down => Some(GamepadButtonType::East),
+            Action::Crouch => Some(GamepadButtonType::RightThumb),
+            Action::Break => Some(GamepadButtonType::RightTrigger2),
+            Action::Place => Some(GamepadButtonType::LeftTrigger2),
+            Action::PickBloc => Some(GamepadButtonType::West),
+            Action::SwitchGameMode => Some(GamepadButtonType::Select),
+            Action::ReleaseCursor => Some(GamepadButtonType::Start),
+            _ => None
+        }
+    }
+    pub fn default_binding(&self) -> Binding {
+        match self {
+            Action::Forward => Binding::Key(KeyCode::KeyW),
+            Action::Backward => Binding::Key(KeyCode::KeyS),
+            Action::Right => Binding::Key(KeyCode::KeyD),
+            Action::Left => Binding::Key(KeyCode::KeyA),
+            Action::Jump => Binding::Key(KeyCode::Space),
+            Action::Run => Binding::Key(KeyCode::ShiftLeft),
+            Action::Down => Binding::Key(KeyCode::ControlLeft),
+            Action::Crouch => Binding::Key(KeyCode::KeyC),
+            Action::Noclip => Binding::Key(KeyCode::KeyN),
+            Action::ToggleAutoJump => Binding::Key(KeyCode::KeyJ),
+            Action::Break => Binding::Mouse(MouseButton::Left),
+            Action::Place => Binding::Mouse(MouseButton::Right),
+            Action::PickBloc => Binding::Mouse(MouseButton::Middle),
+            Action::Hotbar(i) => Binding::Key(match i {
+                0 => KeyCode::Digit1,
+                1 => KeyCode::Digit2,
+                2 => KeyCode::Digit3,
+                3 => KeyCode::Digit4,
+                4 => KeyCode::Digit5,
+                5 => KeyCode::Digit6,
+                6 => KeyCode::Digit7,
+                7 => KeyCode::Digit8,
+                _ => KeyCode::Digit9
+            }),
+            Action::SwitchGameMode => Binding::Key(KeyCode::KeyG),
+            Action::SkipTime => Binding::Key(KeyCode::KeyK),
+            Action::TimeSpeed => Binding::Key(KeyCode::KeyL),
+            Action::SetSpawnPoint => Binding::Key(KeyCode::KeyP),
+            Action::Save => Binding::Key(KeyCode::KeyT),
+            Action::Load => Binding::Key(KeyCode::KeyY),
+            Action::ReleaseCursor => Binding::Key(KeyCode::Escape)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton)
+}
+
+/// The binding is already used by another action
+#[derive(Debug, Clone, Copy)]
+pub struct BindingConflict<B: std::fmt::Debug = Binding> {
+    pub binding: B,
+    pub action: Action
+}
+impl<B: std::fmt::Debug> std::fmt::Display for BindingConflict<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?} is already bound to {:?}", self.binding, self.action)
+    }
+}
+
+/// Binding of every action, saved in `CONFIG_PATH`
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct InputMap {
+    /// Keyboard and mouse bindings
+    bindings: HashMap<Action, Binding>,
+    /// Gamepad bindings, not every action has one
+    #[serde(default)]
+    gamepad_bindings: HashMap<Action, GamepadButtonType>
+}
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::list().into_iter().map(|action| (action, action.default_binding())).collect(),
+            gamepad_bindings: Action::list().into_iter().filter_map(|action| Some((action, action.default_gamepad_binding()?))).collect()
+        }
+    }
+}
+impl InputMap {
+    pub fn get(&self, action: Action) -> Binding {
+        self.bindings.get(&action).copied().unwrap_or(action.default_binding())
+    }
+    pub fn get_gamepad(&self, action: Action) -> Option<GamepadButtonType> {
+        self.gamepad_bindings.get(&action).copied()
+    }
+    /// Action using this binding, if any
+    pub fn action(&self, binding: Binding) -> Option<Action> {
+        self.bindings.iter().find(|(_, b)| **b == binding).map(|(a, _)| *a)
+    }
+    /// Action using this gamepad button, if any
+    pub fn gamepad_action(&self, button: GamepadButtonType) -> Option<Action> {
+        self.gamepad_bindings.iter().find(|(_, b)| **b == button).map(|(a, _)| *a)
+    }
+    /// Bind an action, fails if the binding is already used by another action
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), BindingConflict> {
+        if let Some(other) = self.action(binding) {
+            if other != action {
+                return Err(BindingConflict { binding, action: other })
+            }
+        }
+        self.bindings.insert(action, binding);
+        Ok(())
+    }
+    /// Bind an action to a gamepad button, fails if the button is already used by another action
+    pub fn rebind_gamepad(&mut self, action: Action, button: GamepadButtonType) -> Result<(), BindingConflict<GamepadButtonType>> {
+        if let Some(other) = self.gamepad_action(button) {
+            if other != action {
+                return Err(BindingConflict { binding: button, action: other })
+            }
+        }
+        self.gamepad_bindings.insert(action, button);
+        Ok(())
+    }
+    pub fn unbind_gamepad(&mut self, action: Action) {
+        self.gamepad_bindings.remove(&action);
+    }
+    /// Bind an action to the binding of another one and the other way around
+    pub fn swap(&mut self, action1: Action, action2: Action) {
+        let binding1 = self.get(action1);
+        let binding2 = self.get(action2);
+        self.bindings.insert(action1, binding2);
+        self.bindings.insert(action2, binding1);
+    }
+    pub fn reset(&mut self, action: Action) -> Result<(), BindingConflict> {
+        self.rebind(action, action.default_binding())
+    }
+    /// Pairs of actions sharing the same binding
+    pub fn conflicts(&self) -> Vec<(Action, Action, Binding)> {
+        let mut conflicts = Vec::new();
+        let list = Action::list();
+        for (i, action1) in list.iter().enumerate() {
+            for action2 in list[i+1..].iter() {
+                if self.get(*action1) == self.get(*action2) {
+                    conflicts.push((*action1, *action2, self.get(*action1)));
+                }
+            }
+        }
+        conflicts
+    }
+    /// Pairs of actions sharing the same gamepad button
+    pub fn gamepad_conflicts(&self) -> Vec<(Action, Action, GamepadButtonType)> {
+        let mut conflicts = Vec::new();
+        let list = Action::list();
+        for (i, action1) in list.iter().enumerate() {
+            for action2 in list[i+1..].iter() {
+                if let (Some(b1), Some(b2)) = (self.get_gamepad(*action1), self.get_gamepad(*action2)) {
+                    if b1 == b2 {
+                        conflicts.push((*action1, *action2, b1));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+    /// Load the config, falling back to the default bindings for the missing actions
+    pub fn load() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let content = fs::read_to_string(CONFIG_PATH).ok();
+
+        #[cfg(target_arch = "wasm32")]
+        let content = web_sys::window().unwrap().local_storage().unwrap().unwrap().get_item(CONFIG_PATH).unwrap();
+
+        let mut map = match content {
+            None => return Self::default(),
+            Some(content) => match ron::from_str::<Self>(&content) {
+                Ok(mut map) => {
+                    // Configs saved before gamepad support
+                    if map.gamepad_bindings.is_empty() {
+                        map.gamepad_bindings = Self::default().gamepad_bindings;
+                    }
+                    map
+                },
+                Err(e) => {
+                    warn!("Invalid controls config : {}", e);
+                    return Self::default()
+                }
+            }
+        };
+        for action in Action::list() {
+            map.bindings.entry(action).or_insert(action.default_binding());
+        }
+        for (action1, action2, binding) in map.conflicts() {
+            warn!("{:?} and {:?} are both bound to {:?}", action1, action2, binding);
+        }
+        for (action1, action2, button) in map.gamepad_conflicts() {
+            warn!("{:?} and {:?} are both bound to {:?}", action1, action2, button);
+        }
+        map
+    }
+    pub fn save(&self) {
+        let serialized = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("Cannot serialize controls config : {}", e);
+                return
+            }
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Err(e) = fs::write(CONFIG_PATH, serialized) {
+            warn!("Cannot write controls config : {}", e);
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        web_sys::window().unwrap().local_storage().unwrap().unwrap().set_item(CONFIG_PATH, &serialized).unwrap();
+    }
+}
+
+/// State of the actions for the current frame, from the keyboard, the mouse and all the connected gamepads
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    pub map: Res<'w, InputMap>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    gamepad_axes: Res<'w, Axis<GamepadAxis>>
+}
+impl Actions<'_> {
+    pub fn pressed(&self, action: Action) -> bool {
+        let pressed = match self.map.get(action) {
+            Binding::Key(key) => self.keys.pressed(key),
+            Binding::Mouse(button) => self.mouse.pressed(button)
+        };
+        pressed || match self.map.get_gamepad(action) {
+            None => false,
+            Some(button) => self.gamepads.iter().any(|gamepad| self.gamepad_buttons.pressed(GamepadButton::new(gamepad, button)))
+        }
+    }
+    pub fn just_pressed(&self, action: Action) -> bool {
+        let just_pressed = match self.map.get(action) {
+            Binding::Key(key) => self.keys.just_pressed(key),
+            Binding::Mouse(button) => self.mouse.just_pressed(button)
+        };
+        just_pressed || match self.map.get_gamepad(action) {
+            None => false,
+            Some(button) => self.gamepads.iter().any(|gamepad| self.gamepad_buttons.just_pressed(GamepadButton::new(gamepad, button)))
+        }
+    }
+    fn stick(&self, x: GamepadAxisType, y: GamepadAxisType) -> Vec2 {
+        let mut stick = Vec2::ZERO;
+        for gamepad in self.gamepads.iter() {
+            stick.x += self.gamepad_axes.get(GamepadAxis::new(gamepad, x)).unwrap_or(0.0);
+            stick.y += self.gamepad_axes.get(GamepadAxis::new(gamepad, y)).unwrap_or(0.0);
+        }
+        stick
+    }
+    /// Movement asked by the player, x to the right and y to the front. The length is at most 1
+    pub fn movement(&self) -> Vec2 {
+        let mut mov = self.stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+        if self.pressed(Action::Forward) {
+            mov.y += 1.0;
+        }
+        if self.pressed(Action::Backward) {
+            mov.y -= 1.0;
+        }
+        if self.pressed(Action::Right) {
+            mov.x += 1.0;
+        }
+        if self.pressed(Action::Left) {
+            mov.x -= 1.0;
+        }
+        mov.clamp_length_max(1.0)
+    }
+    /// Right stick position, x to the right and y to the top
+    pub fn look(&self) -> Vec2 {
+        self.stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY).clamp_length_max(1.0)
+    }
+}
+
+/// Save the config each time it is changed
+pub fn save_input_map(
+    map: Res<InputMap>
+) {
+    if map.is_changed() && !map.is_added() {
+        map.save();
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+use bevy::{diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, LogDiagnosticsPlugin, RegisterDiagnostic}, prelude::*};
+use crate::CrackMaterial;
+
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+// To spot leaks in long sessions
+pub struct AssetCountDiagnosticsPlugin;
+impl AssetCountDiagnosticsPlugin {
+    pub const MESHES: DiagnosticPath = DiagnosticPath::const_new("assets/meshes");
+    pub const MATERIALS: DiagnosticPath = DiagnosticPath::const_new("assets/materials");
+    pub const IMAGES: DiagnosticPath = DiagnosticPath::const_new("assets/images");
+}
+impl Plugin for AssetCountDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::MESHES))
+            .register_diagnostic(Diagnostic::new(Self::MATERIALS))
+            .register_diagnostic(Diagnostic::new(Self::IMAGES))
+            .add_plugins(LogDiagnosticsPlugin {
+                wait_duration: LOG_INTERVAL,
+                filter: Some(vec![Self::MESHES, Self::MATERIALS, Self::IMAGES]),
+                ..default()
+            })
+            .add_systems(Update, count_assets);
+    }
+}
+
+pub fn count_assets(
+    mut diagnostics: Diagnostics,
+    meshes: Res<Assets<Mesh>>,
+    (standard_materials, crack_materials): (Res<Assets<StandardMaterial>>, Res<Assets<CrackMaterial>>),
+    images: Res<Assets<Image>>
+) {
+    diagnostics.add_measurement(&AssetCountDiagnosticsPlugin::MESHES, || meshes.len() as f64);
+    diagnostics.add_measurement(&AssetCountDiagnosticsPlugin::MATERIALS, || (standard_materials.len() + crack_materials.len()) as f64);
+    diagnostics.add_measurement(&AssetCountDiagnosticsPlugin::IMAGES, || images.len() as f64);
+}
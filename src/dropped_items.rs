@@ -0,0 +1,219 @@
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use bevy_rapier3d::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::{ChunkPhysic, ChunkPos, Chunks, DefaultGenerator, Inventory, Item, ItemStack, PlayerMarker, BLOCS_PHYSIC_GROUP, CHUNK_Y, SQUARE_UNIT};
+
+pub const ITEMS_PHYSIC_GROUP: Group = Group::GROUP_3;
+
+const ITEM_SIZE: f32 = 0.25;
+const DESPAWN_TIME: f32 = 300.0; // seconds
+const PICKUP_DELAY: f32 = 0.5; // seconds
+const PICKUP_DISTANCE: f32 = 1.2; // from the player center
+const MERGE_DISTANCE: f32 = 0.5;
+const BOB_HEIGHT: f32 = 0.1;
+const BOB_SPEED: f32 = 2.5;
+
+pub struct DroppedItemsPlugin;
+impl Plugin for DroppedItemsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DroppedItemAssets>()
+            .add_event::<DropItem>()
+            .add_systems(Update, spawn_dropped_items)
+            .add_systems(Update, freeze_dropped_items)
+            .add_systems(Update, age_dropped_items.after(freeze_dropped_items))
+            .add_systems(Update, merge_dropped_items)
+            .add_systems(Update, pickup_dropped_items)
+            .add_systems(Update, animate_dropped_items);
+    }
+}
+
+#[derive(Event, Clone)]
+pub struct DropItem {
+    pub stack: ItemStack,
+    pub pos: Vec3,
+    // Seconds since the item was dropped, set when loading a save
+    pub age: f32
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct DroppedItem {
+    pub stack: ItemStack,
+    pub age: f32
+}
+
+// In a chunk without physic, it doesn't move nor age until the player comes back close to it
+#[derive(Component)]
+pub struct FrozenItem;
+
+#[derive(Component)]
+pub struct ItemModel;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DroppedItemSave {
+    pub stack: ItemStack,
+    pub pos: Vec3,
+    pub age: f32
+}
+
+#[derive(Resource, Default)]
+pub struct DroppedItemAssets {
+    mesh: Option<Handle<Mesh>>,
+    materials: HashMap<Item, Handle<StandardMaterial>>
+}
+
+#[derive(Bundle)]
+pub struct DroppedItemBundle {
+    item: DroppedItem,
+    spatial: SpatialBundle,
+    rigid_body: RigidBody,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+    locked_axes: LockedAxes,
+    velocity: Velocity
+}
+impl DroppedItemBundle {
+    pub fn new(stack: ItemStack, pos: Vec3, age: f32) -> Self {
+        // Small random jump so that items don't stay exactly where the bloc was
+        let pop = Vec3::new(rand::random::<f32>() - 0.5, 1.0, rand::random::<f32>() - 0.5) * 2.0;
+        Self {
+            item: DroppedItem { stack, age },
+            spatial: SpatialBundle::from_transform(Transform::from_translation(pos)),
+            rigid_body: RigidBody::Dynamic,
+            collider: Collider::cuboid(ITEM_SIZE/2.0, ITEM_SIZE/2.0, ITEM_SIZE/2.0),
+            // Items only collide with blocs
+            collision_groups: CollisionGroups::new(ITEMS_PHYSIC_GROUP, BLOCS_PHYSIC_GROUP),
+            locked_axes: LockedAxes::ROTATION_LOCKED,
+            velocity: Velocity::linear(pop)
+        }
+    }
+}
+
+pub fn spawn_dropped_items(
+    mut ev_drop: EventReader<DropItem>,
+    mut assets: ResMut<DroppedItemAssets>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut cmds: Commands
+) {
+    for ev in ev_drop.read() {
+        let mesh = assets.mesh.get_or_insert_with(|| {
+            meshes.add(Mesh::from(Cuboid::new(ITEM_SIZE, ITEM_SIZE, ITEM_SIZE)))
+        }).clone();
+        let material = assets.materials.entry(ev.stack.item).or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color_texture: Some(asset_server.load(ev.stack.item.icon_path())),
+                ..default()
+            })
+        }).clone();
+        cmds.spawn(DroppedItemBundle::new(ev.stack, ev.pos, ev.age))
+            .with_children(|parent| {
+                parent.spawn((PbrBundle {
+                    mesh,
+                    material,
+                    ..default()
+                }, ItemModel));
+            });
+    }
+}
+
+// Items in chunks without collider are frozen so they don't fall out of the world
+#[allow(clippy::type_complexity)]
+pub fn freeze_dropped_items(
+    mut items: Query<(Entity, &Transform, &mut RigidBody, &mut Velocity, Has<FrozenItem>), With<DroppedItem>>,
+    chunks: Res<Chunks<DefaultGenerator>>,
+    physics: Query<&ChunkPhysic>,
+    mut cmds: Commands
+) {
+    for (entity, pos, mut rigid_body, mut velocity, frozen) in items.iter_mut() {
+        let chunk_pos = ChunkPos {
+            y: 0,
+            ..ChunkPos::from_translation(pos.translation)
+        };
+        let has_physic = chunks.get(chunk_pos)
+            .and_then(|chunk| physics.get(*chunk).ok())
+            .is_some_and(|physic| *physic != ChunkPhysic::Unloaded);
+        match (frozen, has_physic) {
+            (false, false) => {
+                *rigid_body = RigidBody::Fixed;
+                *velocity = Velocity::zero();
+                cmds.entity(entity).insert(FrozenItem);
+            },
+            (true, true) => {
+                *rigid_body = RigidBody::Dynamic;
+                cmds.entity(entity).remove::<FrozenItem>();
+            },
+            _ => {}
+        }
+    }
+}
+
+pub fn age_dropped_items(
+    mut items: Query<(Entity, &mut DroppedItem, &Transform), Without<FrozenItem>>,
+    time: Res<Time>,
+    mut cmds: Commands
+) {
+    for (entity, mut item, pos) in items.iter_mut() {
+        item.age += time.delta_seconds();
+        if item.age > DESPAWN_TIME || pos.translation.y < -(CHUNK_Y as f32 * SQUARE_UNIT) {
+            cmds.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub fn merge_dropped_items(
+    mut items: Query<(Entity, &mut DroppedItem, &Transform)>,
+    mut cmds: Commands
+) {
+    let mut merged = HashSet::new();
+    let mut combinations = items.iter_combinations_mut();
+    while let Some([(e1, mut item1, pos1), (e2, item2, pos2)]) = combinations.fetch_next() {
+        if merged.contains(&e1) || merged.contains(&e2) {
+            continue
+        }
+        if item1.stack.item != item2.stack.item || pos1.translation.distance(pos2.translation) > MERGE_DISTANCE {
+            continue
+        }
+        if item1.stack.count + item2.stack.count > item1.stack.item.max_stack_size() {
+            continue
+        }
+        item1.stack.count += item2.stack.count;
+        item1.age = item1.age.min(item2.age);
+        cmds.entity(e2).despawn_recursive();
+        merged.insert(e2);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn pickup_dropped_items(
+    mut items: Query<(Entity, &mut DroppedItem, &Transform)>,
+    mut player: Query<(&Transform, &mut Inventory), (With<PlayerMarker>, Without<DroppedItem>)>,
+    mut cmds: Commands
+) {
+    let (player_pos, mut inventory) = player.single_mut();
+    for (entity, mut item, pos) in items.iter_mut() {
+        if item.age < PICKUP_DELAY || pos.translation.distance(player_pos.translation) > PICKUP_DISTANCE {
+            continue
+        }
+        let left = inventory.add(item.stack.item, item.stack.count);
+        if left == 0 {
+            cmds.entity(entity).despawn_recursive();
+        } else {
+            item.stack.count = left;
+        }
+    }
+}
+
+pub fn animate_dropped_items(
+    items: Query<&DroppedItem>,
+    mut models: Query<(&Parent, &mut Transform), With<ItemModel>>
+) {
+    for (parent, mut pos) in models.iter_mut() {
+        let age = match items.get(parent.get()) {
+            Ok(item) => item.age,
+            Err(_) => continue
+        };
+        pos.translation.y = BOB_HEIGHT * (1.0 + (age * BOB_SPEED).sin());
+        pos.rotation = Quat::from_rotation_y(age);
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::{Action, Actions, BlocType, Inventory, Item, PlayerMarker};
+
+pub struct GameModePlugin;
+impl Plugin for GameModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .add_systems(Update, switch_game_mode);
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum GameMode {
+    /// Timed breaking, blocs are taken from the inventory, the player can take damage
+    #[default]
+    Survival,
+    /// Instant breaking, infinite blocs of every type, bloc picker and flight
+    Creative
+}
+impl GameMode {
+    pub fn instant_break(&self) -> bool {
+        *self == Self::Creative
+    }
+    pub fn infinite_blocs(&self) -> bool {
+        *self == Self::Creative
+    }
+    pub fn can_pick_blocs(&self) -> bool {
+        *self == Self::Creative
+    }
+    pub fn can_fly(&self) -> bool {
+        *self == Self::Creative
+    }
+    pub fn takes_damage(&self) -> bool {
+        *self == Self::Survival
+    }
+    /// Sprinting consumes stamina
+    pub fn uses_stamina(&self) -> bool {
+        *self == Self::Survival
+    }
+    /// Broken blocs drop their item
+    pub fn drops_items(&self) -> bool {
+        *self == Self::Survival
+    }
+}
+
+pub fn switch_game_mode(
+    actions: Actions,
+    mut game_mode: ResMut<GameMode>,
+    mut inventory: Query<&mut Inventory, With<PlayerMarker>>
+) {
+    if !actions.just_pressed(Action::SwitchGameMode) {
+        return
+    }
+    *game_mode = match *game_mode {
+        GameMode::Survival => GameMode::Creative,
+        GameMode::Creative => GameMode::Survival
+    };
+    info!("Game mode : {:?}", *game_mode);
+
+    if *game_mode == GameMode::Creative {
+        let mut inventory = inventory.single_mut();
+        for bloc in BlocType::list() {
+            let item = Item::Bloc(bloc);
+            if bloc != BlocType::Air && !inventory.slots.iter().flatten().any(|stack| stack.item == item) {
+                inventory.add(item, 1);
+            }
+        }
+    }
+}
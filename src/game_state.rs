@@ -1,7 +1,7 @@
 use std::{borrow::Borrow, fs, path::Path};
 use bevy::{prelude::*, utils::HashMap};
-use bevy_rapier3d::dynamics::Velocity;
-use crate::{BlocType, ChunkBlocs, ChunkNeighborsAreLinked, ChunkPos, Chunks, DefaultGenerator, Neighbors, PlayerMarker, PosInChunk, Render, CHUNK_X, CHUNK_Y, CHUNK_Z};
+use bevy_rapier3d::prelude::{Collider, CollisionGroups, KinematicCharacterController};
+use crate::{Action, Actions, BlocType, ChunkBlocs, ChunkNeighborsAreLinked, ChunkPos, Chunks, DefaultGenerator, DropItem, DroppedItem, DroppedItemSave, Flight, Frozen, GameMode, Health, Inventory, Neighbors, PlayerMarker, PlayerVelocity, PosInChunk, Render, SpawnPoint, Stamina, Stance, player_collider, set_flight_collisions, PLAYER_HITBOX_HEIGHT, WorldSpawn, WorldTime, CHUNK_X, CHUNK_Y, CHUNK_Z};
 use serde::{Serialize, Deserialize};
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -53,34 +53,79 @@ pub struct ChunkSave {
     pub changes: HashMap<PosInChunk, BlocType>
 }
 
+/// Start of every save file, followed by the save version
+const SAVE_MAGIC: &[u8; 4] = b"MCSV";
+/// Must be incremented when `GameSave` changes, bincode can't read a save made with other fields
+pub const SAVE_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize)]
 pub struct GameSave {
     pub chunks: ChunkSaves,
     pub player_pos: Transform,
-    pub player_linvel: Vec3,
-    pub player_angvel: Vec3
+    pub player_velocity: Vec3,
+    pub player_health: f32,
+    pub player_spawn_point: Option<Vec3>,
+    pub world_spawn: Vec3,
+    pub player_inventory: Inventory,
+    pub dropped_items: Vec<DroppedItemSave>,
+    pub game_mode: GameMode,
+    pub world_time: WorldTime
+}
+impl GameSave {
+    /// Save file content: the magic bytes, the version and the serialized save
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = SAVE_MAGIC.to_vec();
+        bytes.extend(SAVE_VERSION.to_le_bytes());
+        bytes.extend(bincode::serialize(self).unwrap());
+        bytes
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let bytes = bytes.strip_prefix(SAVE_MAGIC).ok_or("the save was made by an older version of the game")?;
+        if bytes.len() < 4 {
+            return Err("the save is truncated".to_string())
+        }
+        let (version, bytes) = bytes.split_at(4);
+        let version = u32::from_le_bytes(version.try_into().unwrap());
+        if version != SAVE_VERSION {
+            return Err(format!("the save version is {} but this game reads version {}", version, SAVE_VERSION))
+        }
+        bincode::deserialize(bytes).map_err(|e| e.to_string())
+    }
 }
 
 pub fn save(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     game_state: Res<GameState>,
     chunk_saves: Res<ChunkSaves>,
-    player: Query<(&Transform, &Velocity), With<PlayerMarker>>
+    player: Query<(&Transform, &PlayerVelocity, &Inventory, &Health, &SpawnPoint), With<PlayerMarker>>,
+    dropped_items: Query<(&DroppedItem, &Transform)>,
+    game_mode: Res<GameMode>,
+    (world_spawn, world_time): (Res<WorldSpawn>, Res<WorldTime>)
 ) {
-    if !keys.just_pressed(KeyCode::KeyT) {
+    if !actions.just_pressed(Action::Save) {
         return
     }
 
-    let (pos, vel) = player.single();
+    let (pos, vel, inventory, health, spawn_point) = player.single();
     let save = GameSave {
         chunks: chunk_saves.clone(),
         player_pos: *pos,
-        player_linvel: vel.linvel,
-        player_angvel: vel.angvel
+        player_velocity: vel.0,
+        player_health: health.0,
+        player_spawn_point: spawn_point.0,
+        world_spawn: world_spawn.0,
+        player_inventory: inventory.clone(),
+        dropped_items: dropped_items.iter().map(|(item, pos)| DroppedItemSave {
+            stack: item.stack,
+            pos: pos.translation,
+            age: item.age
+        }).collect(),
+        game_mode: *game_mode,
+        world_time: *world_time
     };
 
     let path = format!("saves/{:?}.save", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis());
-    let serialized = bincode::serialize(&save).unwrap();
+    let serialized = save.to_bytes();
 
     #[cfg(not(target_arch = "wasm32"))]
     {
@@ -97,16 +142,21 @@ pub fn save(
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn load(
-    keys: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     mut chunks: ResMut<Chunks<DefaultGenerator>>,
     mut game_state: ResMut<GameState>,
     mut chunk_saves: ResMut<ChunkSaves>,
-    mut player: Query<(&mut Transform, &mut Velocity), With<PlayerMarker>>,
+    mut player: Query<(&mut Transform, &mut PlayerVelocity, &mut Inventory, &mut Health, &mut SpawnPoint, Entity), With<PlayerMarker>>,
+    mut movement: Query<(&mut Flight, &mut Stance, &mut Stamina, &mut Collider, &mut CollisionGroups, &mut KinematicCharacterController), With<PlayerMarker>>,
+    dropped_items: Query<Entity, With<DroppedItem>>,
     mut cmds: Commands,
-    mut ev_render: EventWriter<Render>
+    mut ev_render: EventWriter<Render>,
+    mut ev_drop: EventWriter<DropItem>,
+    (mut game_mode, mut world_spawn, mut world_time): (ResMut<GameMode>, ResMut<WorldSpawn>, ResMut<WorldTime>)
 ) {
-    if !keys.just_pressed(KeyCode::KeyY) {
+    if !actions.just_pressed(Action::Load) {
         return
     }
 
@@ -185,9 +235,9 @@ pub fn load(
         bytes
     };
 
-    let game_save: GameSave = match bincode::deserialize(&content) {
+    let game_save = match GameSave::from_bytes(&content) {
         Err(e) => {
-            warn!("Cannot deserialize save : {}", e);
+            warn!("Cannot read save : {}", e);
             return;
         },
         Ok(gs) => gs
@@ -206,7 +256,81 @@ pub fn load(
 
     ev_render.send(Render);
 
-    let (mut pos, mut vel) = player.single_mut();
+    for entity in dropped_items.iter() {
+        cmds.entity(entity).despawn_recursive();
+    }
+    for item in game_save.dropped_items {
+        ev_drop.send(DropItem {
+            stack: item.stack,
+            pos: item.pos,
+            age: item.age
+        });
+    }
+
+    let (mut pos, mut vel, mut inventory, mut health, mut spawn_point, player) = player.single_mut();
     *pos = game_save.player_pos;
-    *vel = Velocity { linvel: game_save.player_linvel, angvel: game_save.player_angvel };
-}
\ No newline at end of file
+    // Wait for the chunks around the saved position before moving
+    cmds.entity(player).insert(Frozen);
+    vel.0 = game_save.player_velocity;
+    *inventory = game_save.player_inventory;
+    inventory.repair();
+    health.0 = game_save.player_health;
+    spawn_point.0 = game_save.player_spawn_point;
+    // The flight and the stance are not saved, the player is back on its feet
+    let (mut flight, mut stance, mut stamina, mut collider, mut collision_groups, mut kcc) = movement.single_mut();
+    *flight = Flight::default();
+    set_flight_collisions(flight.state, &mut collision_groups, &mut kcc);
+    *stance = Stance::default();
+    *collider = player_collider(PLAYER_HITBOX_HEIGHT);
+    *stamina = Stamina::default();
+    world_spawn.0 = game_save.world_spawn;
+    *world_time = game_save.world_time;
+    *game_mode = game_save.game_mode;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_save() -> GameSave {
+        let mut chunks = ChunkSaves::default();
+        let mut chunk = ChunkSave::default();
+        chunk.changes.insert(PosInChunk { x: 1, y: 2, z: 3 }, BlocType::Glowstone);
+        chunks.0.insert(ChunkPos { x: -1, y: 0, z: 4 }, chunk);
+        GameSave {
+            chunks,
+            player_pos: Transform::from_xyz(1.0, 20.0, -3.0),
+            player_velocity: Vec3::ZERO,
+            player_health: 7.5,
+            player_spawn_point: Some(Vec3::new(2.0, 12.0, 2.0)),
+            world_spawn: Vec3::new(0.0, 10.0, 0.0),
+            player_inventory: Inventory::default(),
+            dropped_items: Vec::new(),
+            game_mode: GameMode::default(),
+            world_time: WorldTime::default()
+        }
+    }
+
+    #[test]
+    fn save_round_trip() {
+        let save = GameSave::from_bytes(&game_save().to_bytes()).unwrap();
+        assert_eq!(save.player_pos, Transform::from_xyz(1.0, 20.0, -3.0));
+        assert_eq!(save.player_health, 7.5);
+        assert_eq!(save.player_spawn_point, Some(Vec3::new(2.0, 12.0, 2.0)));
+        let chunk = &save.chunks.0[&ChunkPos { x: -1, y: 0, z: 4 }];
+        assert_eq!(chunk.changes[&PosInChunk { x: 1, y: 2, z: 3 }], BlocType::Glowstone);
+    }
+
+    #[test]
+    fn save_of_another_version_is_rejected() {
+        let bytes = game_save().to_bytes();
+        // Saves made before the versioning
+        assert!(GameSave::from_bytes(&bytes[SAVE_MAGIC.len() + 4..]).is_err());
+        assert!(GameSave::from_bytes(&bytes[..SAVE_MAGIC.len() + 2]).is_err());
+
+        let mut newer = bytes.clone();
+        newer[SAVE_MAGIC.len()..SAVE_MAGIC.len() + 4].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        let error = GameSave::from_bytes(&newer).err().unwrap();
+        assert!(error.contains(&(SAVE_VERSION + 1).to_string()));
+    }
+}
@@ -7,6 +7,16 @@ pub mod player;
 use player::*;
 pub mod game_state;
 use game_state::*;
+pub mod dropped_items;
+use dropped_items::*;
+pub mod game_mode;
+use game_mode::*;
+pub mod controls;
+use controls::*;
+pub mod diagnostics;
+use diagnostics::*;
+pub mod world_time;
+use world_time::*;
 
 fn setup<G: Generator>(
     mut cmds: Commands,
@@ -16,24 +26,12 @@ fn setup<G: Generator>(
     mut ev_render: EventWriter<Render>
 ) {
     // player
-    Player::spawn(&mut cmds);
+    let spawn = find_spawn(&chunks.generator, IVec2::ZERO);
+    cmds.insert_resource(WorldSpawn(spawn));
+    Player::spawn(spawn, &mut cmds);
 
-    // directional 'sun' light
-    cmds.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            //shadows_enabled: true,
-            illuminance: 3000.0,
-            color: Color::rgb(1.0, 1.0, 0.75),
-            ..default()
-        },
-        transform: Transform::default().looking_to(Vec3 { x: 0.5, y: -0.5, z: 0.3 }, Vec3::ZERO),
-        ..default()
-    });
-
-    for x in -1..=1 {
-        for z in -1..=1 {
-            chunks.generate(ChunkPos { x, y: 0, z }, &chunk_saves, &mut game_state, &mut cmds);
-        }
+    for chunk in chunks_around(spawn) {
+        chunks.generate(chunk, &chunk_saves, &mut game_state, &mut cmds);
     }
 
     ev_render.send(Render);
@@ -52,6 +50,11 @@ fn main() {
         .add_plugins(PlayerPlugin)
         .add_plugins(BlocAndChunkPlugin)
         .add_plugins(GameStatePlugin)
+        .add_plugins(DroppedItemsPlugin)
+        .add_plugins(GameModePlugin)
+        .add_plugins(ControlsPlugin)
+        .add_plugins(AssetCountDiagnosticsPlugin)
+        .add_plugins(WorldTimePlugin)
         .add_systems(Startup, setup::<DefaultGenerator>)
         .add_event::<Render>()
         .insert_resource(Chunks::<DefaultGenerator>::new(rand::random()));
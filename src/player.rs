@@ -3,54 +3,66 @@ use bevy_rapier3d::prelude::*;
 
 mod head;
 pub use head::{*, Head};
-
-use crate::{BLOCS_PHYSIC_GROUP, CHUNK_Y, SQUARE_UNIT};
-
-const SPEED: f32 = 0.08;
-const JUMP_SPEED: f32 = 3.0;
-const PLAYER_HITBOX_RADIUS: f32 = 0.33;
-const PLAYER_HITBOX_HEIGHT: f32 = 1.8;
-
-const PLAYER_PHYSIC_GROUP: Group = Group::GROUP_2;
+mod inventory;
+pub use inventory::*;
+mod flight;
+pub use flight::*;
+mod stance;
+pub use stance::*;
+mod autostep;
+pub use autostep::*;
+mod health;
+pub use health::*;
+mod spawn;
+pub use spawn::*;
+
+use crate::{Action, Actions, BLOCS_PHYSIC_GROUP};
+
+/// Walking speed in blocs per second
+const WALK_SPEED: f32 = 4.5;
+/// Horizontal acceleration on the ground, in blocs per second squared
+const GROUND_ACCELERATION: f32 = 60.0;
+/// Horizontal deceleration on the ground when no direction is pressed
+const GROUND_FRICTION: f32 = 40.0;
+/// Horizontal acceleration in the air, lower than on the ground to keep the momentum
+const AIR_ACCELERATION: f32 = 12.0;
+const AIR_FRICTION: f32 = 2.0;
+/// Height of a jump in blocs
+const JUMP_HEIGHT: f32 = 1.25;
+/// Gravity in blocs per second squared
+pub const GRAVITY: f32 = 32.0;
+const TERMINAL_VELOCITY: f32 = 78.0;
+pub const PLAYER_HITBOX_RADIUS: f32 = 0.33;
+pub const PLAYER_HITBOX_HEIGHT: f32 = 1.8;
+
+pub const PLAYER_PHYSIC_GROUP: Group = Group::GROUP_2;
 
 pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(HeadPlugin)
-            .add_systems(Update, move_player);
+            .add_plugins(InventoryPlugin)
+            .add_plugins(StancePlugin)
+            .add_plugins(AutostepPlugin)
+            .add_plugins(HealthPlugin)
+            .add_plugins(SpawnPlugin)
+            .add_systems(Update, toggle_flight.before(update_stance))
+            .add_systems(Update, update_stance)
+            .add_systems(FixedUpdate, move_player)
+            .add_systems(Update, apply_player_movement);
     }
 }
 
 #[derive(Component)]
 pub struct PlayerMarker;
 
-#[derive(Component)]
-pub struct PlayerKeys {
-    forward: KeyCode,
-    backward: KeyCode,
-    right: KeyCode,
-    left: KeyCode,
-    jump: KeyCode,
-    run: KeyCode
-}
-impl Default for PlayerKeys {
-    fn default() -> Self {
-        Self {
-            forward: KeyCode::KeyW,
-            backward: KeyCode::KeyS,
-            right: KeyCode::KeyD,
-            left: KeyCode::KeyA,
-            jump: KeyCode::Space,
-            run: KeyCode::ShiftLeft
-        }
-    }
-}
-
+/// Velocity of the player in blocs per second
 #[derive(Component, Default, Clone)]
-pub struct PlayerVelocity (Vec3);
+pub struct PlayerVelocity(pub Vec3);
 
-#[derive(Component)]
-pub struct TouchedGroudLastFrame(bool);
+/// Translation computed by the fixed updates since the last physic step
+#[derive(Component, Default)]
+pub struct PendingTranslation(Vec3);
 
 #[derive(Bundle)]
 pub struct Player {
@@ -58,108 +70,175 @@ pub struct Player {
     // collider_mass_properties: ColliderMassProperties,
     // friction: Friction,
     // damping: Damping,
-    gravity_scale: GravityScale,
     vel: PlayerVelocity,
+    pending_translation: PendingTranslation,
     kcc: KinematicCharacterController,
     marker: PlayerMarker,
     spatial: SpatialBundle,
     rigid_body: RigidBody,
-    jump_impulse: ExternalImpulse,
     sleeping: Sleeping,
     locked_axes: LockedAxes,
-    keys: PlayerKeys,
     collision_groups: CollisionGroups,
-    touched_groud_last_frame: TouchedGroudLastFrame
+    inventory: Inventory,
+    flight: Flight,
+    stance: Stance,
+    stamina: Stamina,
+    health: Health,
+    spawn_point: SpawnPoint
 }
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(pos: Vec3) -> Self {
         Self {
-            collider: Collider::round_cylinder((PLAYER_HITBOX_HEIGHT/2.0)-0.1, PLAYER_HITBOX_RADIUS-0.1, 0.1),
+            collider: player_collider(PLAYER_HITBOX_HEIGHT),
             // collider_mass_properties: ColliderMassProperties::Density(1.0),
             // friction: Friction { coefficient: 2.5, combine_rule: CoefficientCombineRule::Multiply },
             // damping: Damping {
             //     linear_damping: 1.0,
             //     angular_damping: 0.0
             // },
-            gravity_scale: GravityScale(4.0),
             vel: PlayerVelocity::default(),
+            pending_translation: PendingTranslation::default(),
             kcc: KinematicCharacterController {
                 offset: CharacterLength::Absolute(0.05),
                 ..Default::default()
             },
             marker: PlayerMarker,
-            spatial: SpatialBundle::from_transform(Transform::from_xyz(0.0, (CHUNK_Y as f32)*SQUARE_UNIT, 0.0)),
-            rigid_body: RigidBody::Dynamic,
-            jump_impulse: ExternalImpulse::default(),
+            spatial: SpatialBundle::from_transform(Transform::from_translation(pos)),
+            rigid_body: RigidBody::KinematicPositionBased,
             sleeping: Sleeping::disabled(),
             locked_axes: LockedAxes::ROTATION_LOCKED,
-            keys: PlayerKeys::default(),
             collision_groups: CollisionGroups::new(PLAYER_PHYSIC_GROUP, Group::ALL),
-            touched_groud_last_frame: TouchedGroudLastFrame(false)
+            inventory: Inventory::default(),
+            flight: Flight::default(),
+            stance: Stance::default(),
+            stamina: Stamina::default(),
+            health: Health::default(),
+            spawn_point: SpawnPoint::default()
         }
     }
-    pub fn spawn(cmds: &mut Commands) {
-        cmds.spawn(Self::new())
+    /// Spawn the player frozen until the world around `pos` is ready
+    pub fn spawn(pos: Vec3, cmds: &mut Commands) {
+        cmds.spawn((Self::new(pos), Frozen))
             .with_children(|parent| {
                 parent.spawn(Head::default());
             });
     }
 }
 
-pub fn move_player(
-    mut player: Query<(&mut ExternalImpulse, &Transform, &PlayerKeys, &mut TouchedGroudLastFrame, &mut KinematicCharacterController, Entity), With<PlayerMarker>>,
-    rapier_ctx: Res<RapierContext>,
-    keys: Res<ButtonInput<KeyCode>>
-) {
-    let (mut jump_impulse, pos, player_keys, mut touched_groud_last_frame, mut kcc, player) = player.single_mut();
-    let mut mov = Vec3::ZERO;
-    if keys.pressed(player_keys.forward) || keys.just_pressed(player_keys.forward) {
-        mov -= pos.local_z().xyz();
-    }
-    if keys.pressed(player_keys.backward) {
-        mov += pos.local_z().xyz();
-    }
-    if keys.pressed(player_keys.right) {
-        mov += pos.local_x().xyz();
-    }
-    if keys.pressed(player_keys.left) {
-        mov -= pos.local_x().xyz();
-    }
-
-    mov = mov.normalize_or_zero();
-
-    let ground = rapier_ctx.intersection_with_shape(
-        pos.translation + Vec3::new(0.0, -PLAYER_HITBOX_HEIGHT/2.0, 0.0),
+/// Bloc under the feet of a player whose center is at `center`
+fn ground_under(rapier_ctx: &RapierContext, center: Vec3, hitbox_height: f32) -> Option<Entity> {
+    rapier_ctx.intersection_with_shape(
+        center + Vec3::new(0.0, -hitbox_height/2.0, 0.0),
         Quat::IDENTITY,
         &Collider::cylinder(0.1, PLAYER_HITBOX_RADIUS-0.1),
         QueryFilter::default().groups(
             CollisionGroups::new(PLAYER_PHYSIC_GROUP, BLOCS_PHYSIC_GROUP)
         )
-    );
+    )
+}
 
-    let is_on_ground = match ground {
-        None => false,
-        Some(ground) => match rapier_ctx.contact_pair(ground, player) {
-            None => false,
-            Some(pair) => pair.has_any_active_contacts()
-        }
-    };
+/// Move `velocity` toward `target`, changing it by at most `max_delta`
+fn approach(velocity: Vec3, target: Vec3, max_delta: f32) -> Vec3 {
+    velocity + (target - velocity).clamp_length_max(max_delta)
+}
 
-    if keys.pressed(player_keys.run) || keys.just_pressed(player_keys.run) {
-        mov *= 2.0
+pub fn move_player(
+    mut player: Query<(&mut PlayerVelocity, &mut PendingTranslation, &Transform, Option<&KinematicCharacterControllerOutput>, &Flight, &Stance, &Health, Has<Frozen>), With<PlayerMarker>>,
+    rapier_ctx: Res<RapierContext>,
+    actions: Actions,
+    time: Res<Time>,
+    autostep: Res<Autostep>,
+    mut ev_landed: EventWriter<PlayerLanded>
+) {
+    let (mut vel, mut pending, pos, output, flight, stance, health, frozen) = player.single_mut();
+    if health.is_dead() || frozen {
+        vel.0 = Vec3::ZERO;
+        return
+    }
+    let dt = time.delta_seconds();
+    let input = actions.movement();
+    let wished_dir = (pos.local_x().xyz() * input.x) - (pos.local_z().xyz() * input.y);
+
+    if flight.is_flying() {
+        let mut target = wished_dir;
+        if actions.pressed(Action::Jump) {
+            target += Vec3::Y;
+        }
+        if actions.pressed(Action::Down) {
+            target -= Vec3::Y;
+        }
+        if actions.pressed(Action::Run) {
+            target *= 2.0
+        }
+        vel.0 = approach(vel.0, target*flight.speed, GROUND_ACCELERATION*dt);
+        pending.0 += vel.0*dt;
+        return
     }
 
-    kcc.translation = Some(mov*SPEED);
+    let is_on_ground = output.is_some_and(|output| output.grounded);
 
-    if keys.pressed(player_keys.jump) {
-        if is_on_ground && touched_groud_last_frame.0 {
-            jump_impulse.impulse = Vec3::new(0.0, JUMP_SPEED, 0.0);
+    // Horizontal movement
+    let horizontal = Vec3::new(vel.0.x, 0.0, vel.0.z);
+    let target = wished_dir*WALK_SPEED*stance.speed_multiplier();
+    let (acceleration, friction) = if is_on_ground {
+        (GROUND_ACCELERATION, GROUND_FRICTION)
+    } else {
+        (AIR_ACCELERATION, AIR_FRICTION)
+    };
+    let horizontal = if target == Vec3::ZERO {
+        approach(horizontal, Vec3::ZERO, friction*dt)
+    } else {
+        approach(horizontal, target, acceleration*dt)
+    };
+    vel.0.x = horizontal.x;
+    vel.0.z = horizontal.z;
+
+    // Vertical movement
+    if let Some(output) = output {
+        // Stop going up when hitting a ceiling
+        if output.desired_translation.y > 0.0 && output.effective_translation.y < output.desired_translation.y*0.5 {
+            vel.0.y = vel.0.y.min(0.0);
+        }
+    }
+    if is_on_ground && vel.0.y <= 0.0 {
+        // The controller is kept at -1 bloc per second when standing on the ground
+        if vel.0.y < -1.0 {
+            ev_landed.send(PlayerLanded { speed: -vel.0.y });
+        }
+        let auto_jump = !stance.crouching && autostep.should_jump(&rapier_ctx, pos.translation + pending.0, stance.hitbox_height(), wished_dir);
+        vel.0.y = if actions.pressed(Action::Jump) || auto_jump {
+            (2.0*GRAVITY*JUMP_HEIGHT).sqrt()
         } else {
+            // Keep the controller pressed against the ground so it stays grounded
+            -1.0
+        };
+    } else {
+        vel.0.y = (vel.0.y - GRAVITY*dt).max(-TERMINAL_VELOCITY);
+    }
+
+    let mut mov = vel.0*dt;
 
+    // Don't walk off bloc edges while crouching
+    if stance.crouching && is_on_ground {
+        let height = stance.hitbox_height();
+        let feet = pos.translation + pending.0;
+        if ground_under(&rapier_ctx, feet + Vec3::new(mov.x, 0.0, 0.0), height).is_none() {
+            mov.x = 0.0;
+            vel.0.x = 0.0;
+        }
+        if ground_under(&rapier_ctx, feet + Vec3::new(mov.x, 0.0, mov.z), height).is_none() {
+            mov.z = 0.0;
+            vel.0.z = 0.0;
         }
-    } else {
-        jump_impulse.impulse = Vec3::ZERO;
     }
 
-    touched_groud_last_frame.0 = is_on_ground;
-}
\ No newline at end of file
+    pending.0 += mov;
+}
+
+/// Give the translation accumulated by the fixed updates to the character controller
+pub fn apply_player_movement(
+    mut player: Query<(&mut PendingTranslation, &mut KinematicCharacterController), With<PlayerMarker>>
+) {
+    let (mut pending, mut kcc) = player.single_mut();
+    kcc.translation = Some(std::mem::take(&mut pending.0));
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::{player_collider, Action, Actions, HeadMarker, PlayerMarker, PlayerVelocity, BLOCS_PHYSIC_GROUP, PLAYER_HITBOX_RADIUS, PLAYER_PHYSIC_GROUP};
+
+/// Highest ledge climbed without jumping, enough for half blocs
+const STEP_HEIGHT: f32 = 0.55;
+/// Distance in front of the hitbox where ledges are searched for auto-jump
+const LEDGE_DISTANCE: f32 = 0.3;
+
+pub struct AutostepPlugin;
+impl Plugin for AutostepPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Autostep>()
+            .add_systems(Update, toggle_auto_jump)
+            .add_systems(Update, apply_autostep.after(toggle_auto_jump))
+            .add_systems(Update, smooth_step);
+    }
+}
+
+/// How the player climbs ledges
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct Autostep {
+    /// Ledges up to this height (in blocs) are climbed by just walking into them
+    pub step_height: f32,
+    /// Jump automatically when walking into a ledge of one bloc
+    pub auto_jump: bool
+}
+impl Default for Autostep {
+    fn default() -> Self {
+        Self {
+            step_height: STEP_HEIGHT,
+            auto_jump: true
+        }
+    }
+}
+impl Autostep {
+    pub fn character_autostep(&self) -> Option<CharacterAutostep> {
+        if self.step_height <= 0.0 {
+            return None
+        }
+        Some(CharacterAutostep {
+            max_height: CharacterLength::Absolute(self.step_height),
+            min_width: CharacterLength::Absolute(0.1),
+            include_dynamic_bodies: false
+        })
+    }
+    /// True if the player walking in `dir` is facing a ledge too high to step on but free to jump on
+    pub fn should_jump(&self, rapier_ctx: &RapierContext, center: Vec3, hitbox_height: f32, dir: Vec3) -> bool {
+        let dir = Vec3::new(dir.x, 0.0, dir.z).normalize_or_zero();
+        if !self.auto_jump || dir == Vec3::ZERO {
+            return false
+        }
+        let filter = QueryFilter::default().groups(CollisionGroups::new(PLAYER_PHYSIC_GROUP, BLOCS_PHYSIC_GROUP));
+        let feet = center - Vec3::Y*(hitbox_height/2.0);
+
+        // Something higher than a step in front of the feet
+        let ledge = rapier_ctx.intersection_with_shape(
+            feet + dir*(PLAYER_HITBOX_RADIUS+LEDGE_DISTANCE) + Vec3::Y*((self.step_height + 1.0)/2.0),
+            Quat::IDENTITY,
+            &Collider::ball(0.1),
+            filter
+        );
+        if ledge.is_none() {
+            return false
+        }
+
+        // Room for the player on top of it
+        rapier_ctx.intersection_with_shape(
+            center + dir*LEDGE_DISTANCE + Vec3::Y*1.05,
+            Quat::IDENTITY,
+            &player_collider(hitbox_height),
+            filter
+        ).is_none()
+    }
+}
+
+pub fn toggle_auto_jump(
+    actions: Actions,
+    mut autostep: ResMut<Autostep>
+) {
+    if !actions.just_pressed(Action::ToggleAutoJump) {
+        return
+    }
+    autostep.auto_jump = !autostep.auto_jump;
+    info!("Auto-jump : {}", autostep.auto_jump);
+}
+
+pub fn apply_autostep(
+    autostep: Res<Autostep>,
+    mut kcc: Query<&mut KinematicCharacterController, With<PlayerMarker>>
+) {
+    for mut kcc in kcc.iter_mut() {
+        if autostep.is_changed() || kcc.is_added() {
+            kcc.autostep = autostep.character_autostep();
+            kcc.snap_to_ground = Some(CharacterLength::Absolute(autostep.step_height.max(0.1)));
+        }
+    }
+}
+
+/// Lower the camera when the player steps up a ledge, `animate_head` then brings it back up smoothly
+pub fn smooth_step(
+    player: Query<(&KinematicCharacterControllerOutput, &PlayerVelocity), With<PlayerMarker>>,
+    mut head: Query<&mut Transform, With<HeadMarker>>,
+    autostep: Res<Autostep>
+) {
+    let (output, vel) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return
+    };
+    let step = output.effective_translation.y;
+    if output.grounded && vel.0.y <= 0.0 && step > 0.01 && step <= autostep.step_height + 0.01 {
+        head.single_mut().translation.y -= step;
+    }
+}
@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::{Action, Actions, GameMode, PlayerMarker, PlayerVelocity, PLAYER_PHYSIC_GROUP};
+
+/// Flight speed in blocs per second
+const FLY_SPEED: f32 = 11.0;
+/// Max time (in seconds) between two jump presses to toggle flight
+const DOUBLE_TAP_DELAY: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlightState {
+    Walking,
+    Flying,
+    /// Flying without collisions
+    Noclip
+}
+
+#[derive(Component)]
+pub struct Flight {
+    pub state: FlightState,
+    /// Speed in blocs per second while flying
+    pub speed: f32,
+    /// Elapsed time of the last jump press, to detect double taps
+    last_jump_press: Option<f32>
+}
+impl Default for Flight {
+    fn default() -> Self {
+        Self {
+            state: FlightState::Walking,
+            speed: FLY_SPEED,
+            last_jump_press: None
+        }
+    }
+}
+impl Flight {
+    pub fn is_flying(&self) -> bool {
+        self.state != FlightState::Walking
+    }
+}
+
+/// Toggle flight with a double tap on jump and noclip with its own key
+pub fn toggle_flight(
+    mut player: Query<(&mut Flight, &mut PlayerVelocity, &mut CollisionGroups, &mut KinematicCharacterController), With<PlayerMarker>>,
+    actions: Actions,
+    time: Res<Time>,
+    game_mode: Res<GameMode>
+) {
+    let (mut flight, mut vel, mut collision_groups, mut kcc) = player.single_mut();
+    let mut state = flight.state;
+    if !game_mode.can_fly() {
+        state = FlightState::Walking;
+    } else {
+        if actions.just_pressed(Action::Jump) {
+            let now = time.elapsed_seconds();
+            match flight.last_jump_press {
+                Some(last) if now - last < DOUBLE_TAP_DELAY => {
+                    state = if state == FlightState::Walking {
+                        FlightState::Flying
+                    } else {
+                        FlightState::Walking
+                    };
+                    flight.last_jump_press = None;
+                },
+                _ => flight.last_jump_press = Some(now)
+            }
+        }
+        if actions.just_pressed(Action::Noclip) {
+            state = if state == FlightState::Noclip {
+                FlightState::Flying
+            } else {
+                FlightState::Noclip
+            };
+        }
+    }
+
+    if state == flight.state {
+        return
+    }
+    flight.state = state;
+    vel.0 = Vec3::ZERO;
+    set_flight_collisions(state, &mut collision_groups, &mut kcc);
+}
+
+/// The player collides with everything, except in noclip
+pub fn set_flight_collisions(state: FlightState, collision_groups: &mut CollisionGroups, kcc: &mut KinematicCharacterController) {
+    if state == FlightState::Noclip {
+        *collision_groups = CollisionGroups::new(PLAYER_PHYSIC_GROUP, Group::NONE);
+        kcc.filter_groups = Some(*collision_groups);
+    } else {
+        *collision_groups = CollisionGroups::new(PLAYER_PHYSIC_GROUP, Group::ALL);
+        kcc.filter_groups = None;
+    }
+}
@@ -1,18 +1,25 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
-use crate::{remove_bloc, BaseMaterial, BlocFaces, BlocType, ChunkPos, ChunkSaves, Cracks, DestructionLevel, FaceMarker, GameState, Neighbors, NextMaterial, PosInChunk, BLOCS_PHYSIC_GROUP};
+use crate::{distance_fog, remove_bloc, Action, Actions, set_bloc, BlocAssets, BlocFaces, BlocHit, BlocType, raycast, ChunkBlocs, ChunkPos, Chunks, DefaultGenerator, ChunkSaves, CrackMaterial, CrackOverlay, Cracks, DropItem, GameMode, GameState, player_is_alive, Inventory, Item, ItemStack, Neighbors, HOTBAR_SIZE, CRACK_STAGES, PlayerMarker, PosInChunk, BLOCS_PHYSIC_GROUP, PLAYER_PHYSIC_GROUP, SQUARE_UNIT};
 
 pub mod camera;
 pub use camera::*;
+pub mod target;
+pub use target::*;
 
 const RANGE: f32 = 5.0;
+/// Time (in seconds) between two bloc breaks when breaking is instant
+const INSTANT_BREAK_COOLDOWN: f32 = 0.25;
 
 pub struct HeadPlugin;
 impl Plugin for HeadPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(CameraPlugin)
-            .add_systems(Update, destroy_bloc)
-            .add_systems(Update, reset_destruction_lvl);
+            .add_plugins(TargetPlugin)
+            .add_systems(Update, destroy_bloc.run_if(player_is_alive))
+            .add_systems(Update, place_bloc.run_if(player_is_alive))
+            .add_systems(Update, pick_bloc.run_if(player_is_alive))
+            .add_systems(Update, show_cracks.after(destroy_bloc));
     }
 }
 
@@ -20,6 +27,10 @@ impl Plugin for HeadPlugin {
 /// (bloc_entity, advancement_between_0_and_1)
 pub struct BlocBeingDestroyed(Option<(Entity, f32)>);
 
+#[derive(Component, Default)]
+/// Time (in seconds) left before the next instant break
+pub struct BreakCooldown(f32);
+
 #[derive(Component)]
 pub struct HeadMarker;
 
@@ -27,8 +38,10 @@ pub struct HeadMarker;
 pub struct Head {
     marker: HeadMarker,
     cam: Camera3dBundle,
+    fog: FogSettings,
     config: CameraConfig,
-    bloc_being_destroyed: BlocBeingDestroyed
+    bloc_being_destroyed: BlocBeingDestroyed,
+    break_cooldown: BreakCooldown
 }
 impl Default for Head {
     fn default() -> Self {
@@ -38,148 +51,212 @@ impl Default for Head {
                 transform: Transform::from_xyz(0.0, 0.5, 0.0),
                 ..Default::default()
             },
+            fog: distance_fog(),
             config: CameraConfig::default(),
-            bloc_being_destroyed: BlocBeingDestroyed(None)
+            bloc_being_destroyed: BlocBeingDestroyed(None),
+            break_cooldown: BreakCooldown(0.0)
         }
     }
 }
 
+pub fn targeted_bloc(head: &GlobalTransform, game_state: &GameState, chunks: &Chunks<DefaultGenerator>, chunks_query: &Query<&ChunkBlocs>) -> Option<(Entity, BlocHit)> {
+    let hit = raycast(game_state, head.translation(), head.forward(), RANGE)?;
+    let bloc = chunks.get_bloc(hit.chunk_pos, &hit.pos, chunks_query)?;
+    Some((bloc, hit))
+}
+
 pub fn destroy_bloc(
     head: Query<&GlobalTransform, With<HeadMarker>>,
-    rapier_ctx: Res<RapierContext>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    (chunks, chunks_query): (Res<Chunks<DefaultGenerator>>, Query<&ChunkBlocs>),
+    bloc_assets: Res<BlocAssets>,
     blocs: (Query<'_, '_, &'_ mut BlocType>, Query<(Entity,&mut Neighbors,&mut BlocFaces)>, Query<(&PosInChunk, &Parent), With<BlocType>>, Query<&ChunkPos>),
-    // mut blocs_types_query: Query<'_, '_, &'_ mut BlocType>,
-    // mut blocs: Query<(Entity,&mut Neighbors,&mut BlocFaces)>,
-    // blocs_pos_parent_query: Query<(&PosInChunk, &Parent), With<BlocType>>,
-    // chunk_pos_query: Query<&ChunkPos>,
     mut game_state: ResMut<GameState>,
     mut changes: ResMut<ChunkSaves>,
-    mut faces: Query<(&mut Handle<StandardMaterial>, &BaseMaterial, &mut NextMaterial, &mut DestructionLevel), With<FaceMarker>>,
     mut cmds: Commands,
-    mut bloc_being_destroyed: Query<&mut BlocBeingDestroyed, With<HeadMarker>>,
-    time: Res<Time>,
-    images: Res<Assets<Image>>,
-    cracks: Res<Cracks>,
-    #[cfg(not(target_arch = "wasm32"))]
-    mouse: Res<ButtonInput<MouseButton>>,
-    #[cfg(target_arch = "wasm32")]
-    wasm_mouse_tracker: Res<WasmMouseTracker>,
+    mut bloc_being_destroyed: Query<(&mut BlocBeingDestroyed, &mut BreakCooldown), With<HeadMarker>>,
+    (time, game_mode): (Res<Time>, Res<GameMode>),
+    mut ev_drop: EventWriter<DropItem>,
+    actions: Actions
 ) {
-    #[cfg(not(target_arch = "wasm32"))]
-    if !mouse.pressed(MouseButton::Left) {
-        bloc_being_destroyed.single_mut().0 = None;
-        return;
-    }
-    #[cfg(target_arch = "wasm32")]
-    if !wasm_mouse_tracker.is_mouse_down() {
-        bloc_being_destroyed.single_mut().0 = None;
+    let (mut bloc_being_destroyed, mut break_cooldown) = bloc_being_destroyed.single_mut();
+    break_cooldown.0 = (break_cooldown.0 - time.delta_seconds()).max(0.0);
+
+    if !actions.pressed(Action::Break) {
+        bloc_being_destroyed.0 = None;
+        break_cooldown.0 = 0.0;
         return;
     }
 
     let (mut blocs_types_query, mut blocs, blocs_pos_parent_query, chunk_pos_query) = blocs;
 
     let global_pos = head.single();
-    let (selected_bloc, distance) = match rapier_ctx.cast_ray(
-        global_pos.translation(),
-        global_pos.forward(),
-        RANGE,
-        true,
-        QueryFilter::default().groups(
-            CollisionGroups::new(Group::ALL, BLOCS_PHYSIC_GROUP)
-        )
-    ) {
+    let selected_bloc = match targeted_bloc(global_pos, &game_state, &chunks, &chunks_query) {
         None => return,
-        Some(sb) => sb
+        Some((bloc, _)) => bloc
     };
 
-    let mut bloc_being_destroyed = bloc_being_destroyed.single_mut();
-    let (bbd, crack_lvl) = match bloc_being_destroyed.0 {
-        None => {
-            ((selected_bloc, 0.0),Some(0))
-        },
-        Some(mut bbd) => {
-            if bbd.0 != selected_bloc {
-                ((selected_bloc, 0.0), Some(0))
-            } else {
-                let old_time = bbd.1;
-                bbd.1 += time.delta_seconds();
-                let crack = if old_time < 0.2 && bbd.1 >= 0.2 {
-                    Some(1)
-                } else if old_time < 0.4 && bbd.1 >= 0.4 {
-                    Some(2)
-                } else if old_time < 0.6 && bbd.1 >= 0.6 {
-                    Some(3)
-                } else if old_time < 0.8 && bbd.1 >= 0.8 {
-                    Some(4)
-                } else {
-                    None
-                };
-                (bbd, crack)
-            }
-        }
-    };
+    let bloc_type = *blocs_types_query.get(selected_bloc).unwrap();
 
-    if let Some(crack) = crack_lvl {
-        let crack = images.get(cracks.0[crack].id()).unwrap();
-        let bloc = blocs.get_mut(selected_bloc).unwrap();
-        for x in bloc.2.0.iter() {
-            let (_, base_mat, mut next_mat, mut destruction_lvl) = faces.get_mut(*x).unwrap();
-            let mut material = materials.get(base_mat.0.id()).unwrap().clone();
-            let mut img = match images.get(material.base_color_texture.unwrap().id()) {
-                Some(img) => img.clone(),
-                None => continue
-            };
-            for (i, c) in img.data.chunks_mut(4).zip(crack.data.chunks(4)) {
-                let c3 = c[3] as u16;
-                *i.get_mut(0).unwrap() = (((i[0] as u16 * (255-c3)) + (c[0] as u16 * c3)) / (255)) as u8;
-                *i.get_mut(1).unwrap() = (((i[1] as u16 * (255-c3)) + (c[1] as u16 * c3)) / (255)) as u8;
-                *i.get_mut(2).unwrap() = (((i[2] as u16 * (255-c3)) + (c[2] as u16 * c3)) / (255)) as u8;
-            }
-            material.base_color_texture = Some(asset_server.add(img));
-            next_mat.0 = Some(asset_server.add(material));
-            *destruction_lvl = match crack_lvl {
-                None => DestructionLevel::Zero,
-                Some(0) => DestructionLevel::One,
-                Some(1) => DestructionLevel::Two,
-                Some(2) => DestructionLevel::Three,
-                Some(3) => DestructionLevel::Four,
-                Some(4) => DestructionLevel::Five,
-                _ => unreachable!()
-            };
+    if game_mode.instant_break() {
+        if break_cooldown.0 > 0.0 {
+            return
         }
+        break_cooldown.0 = INSTANT_BREAK_COOLDOWN;
+        let neighbors = blocs.get_mut(selected_bloc).unwrap().1.clone();
+        remove_bloc(selected_bloc, &neighbors, &mut blocs, &mut blocs_types_query, &blocs_pos_parent_query, &chunk_pos_query, &mut game_state, &mut changes, &mut cmds, &bloc_assets);
+        bloc_being_destroyed.0 = None;
+        return
     }
 
+    let bbd = match bloc_being_destroyed.0 {
+        Some(bbd) if bbd.0 == selected_bloc => (bbd.0, bbd.1 + time.delta_seconds() / bloc_type.break_time()),
+        _ => (selected_bloc, 0.0)
+    };
+
     if bbd.1 >= 1.0 {
+        if let (true, Some(item)) = (game_mode.drops_items(), Item::from_broken_bloc(bloc_type)) {
+            let (pos, parent) = blocs_pos_parent_query.get(selected_bloc).unwrap();
+            let chunk_pos = chunk_pos_query.get(parent.get()).unwrap();
+            ev_drop.send(DropItem {
+                stack: ItemStack { item, count: 1 },
+                pos: pos.to_translation(*chunk_pos),
+                age: 0.0
+            });
+        }
         let neighbors = blocs.get_mut(selected_bloc).unwrap().1.clone();
-        remove_bloc(selected_bloc, &neighbors, &mut blocs, &mut blocs_types_query, &blocs_pos_parent_query, &chunk_pos_query, &mut game_state, &mut changes, &mut cmds, &asset_server, &mut meshes, &mut materials);
+        remove_bloc(selected_bloc, &neighbors, &mut blocs, &mut blocs_types_query, &blocs_pos_parent_query, &chunk_pos_query, &mut game_state, &mut changes, &mut cmds, &bloc_assets);
         bloc_being_destroyed.0 = None;
     } else {
         bloc_being_destroyed.0 = Some(bbd);
     }
 }
 
-pub fn reset_destruction_lvl(
-    mut faces: Query<(&Parent, &mut DestructionLevel, &BaseMaterial, &mut NextMaterial), With<FaceMarker>>,
-    bbd: Query<&BlocBeingDestroyed, With<HeadMarker>>
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn place_bloc(
+    head: Query<&GlobalTransform, With<HeadMarker>>,
+    (rapier_ctx, chunks, chunks_query): (Res<RapierContext>, Res<Chunks<DefaultGenerator>>, Query<&ChunkBlocs>),
+    bloc_assets: Res<BlocAssets>,
+    blocs: (Query<'_, '_, &'_ mut BlocType>, Query<(Entity,&mut Neighbors,&mut BlocFaces)>, Query<(&PosInChunk, &Parent), With<BlocType>>, Query<&ChunkPos>),
+    mut game_state: ResMut<GameState>,
+    mut changes: ResMut<ChunkSaves>,
+    mut inventory: Query<&mut Inventory, With<PlayerMarker>>,
+    game_mode: Res<GameMode>,
+    mut cmds: Commands,
+    actions: Actions
+) {
+    if !actions.just_pressed(Action::Place) {
+        return;
+    }
+
+    let (mut blocs_types_query, mut blocs, blocs_pos_parent_query, chunk_pos_query) = blocs;
+
+    let mut inventory = inventory.single_mut();
+    let new_type = match inventory.selected_stack().and_then(|stack| stack.item.bloc()) {
+        None => return,
+        Some(t) => t
+    };
+
+    let global_pos = head.single();
+    let (selected_bloc, hit) = match targeted_bloc(global_pos, &game_state, &chunks, &chunks_query) {
+        None => return,
+        Some(target) => target
+    };
+    let (bloc_center, face) = (hit.translation(), hit.face);
+
+    let target = match blocs.get(selected_bloc).unwrap().1.get_with_direction(&face) {
+        None => return,
+        Some(t) => t
+    };
+    if blocs_types_query.get(target).unwrap() != &BlocType::Air {
+        return
+    }
+
+    // Don't place a bloc inside the player
+    if rapier_ctx.intersection_with_shape(
+        bloc_center + (face.normal() * SQUARE_UNIT),
+        Quat::IDENTITY,
+        &Collider::cuboid(SQUARE_UNIT/2.0, SQUARE_UNIT/2.0, SQUARE_UNIT/2.0),
+        QueryFilter::default().groups(
+            CollisionGroups::new(BLOCS_PHYSIC_GROUP, PLAYER_PHYSIC_GROUP)
+        )
+    ).is_some() {
+        return
+    }
+
+    if !game_mode.infinite_blocs() {
+        inventory.take_selected();
+    }
+    let neighbors = blocs.get(target).unwrap().1.clone();
+    set_bloc(target, new_type, &neighbors, &mut blocs, &mut blocs_types_query, &blocs_pos_parent_query, &chunk_pos_query, &mut game_state, &mut changes, &mut cmds, &bloc_assets);
+}
+
+/// Put the targeted bloc in the hotbar
+pub fn pick_bloc(
+    head: Query<&GlobalTransform, With<HeadMarker>>,
+    (game_state, chunks, chunks_query): (Res<GameState>, Res<Chunks<DefaultGenerator>>, Query<&ChunkBlocs>),
+    blocs_types_query: Query<&BlocType>,
+    mut inventory: Query<&mut Inventory, With<PlayerMarker>>,
+    game_mode: Res<GameMode>,
+    actions: Actions
 ) {
-    let bbd = bbd.single();
-    for (parent, mut lvl, base_mat, mut next_mat) in faces.iter_mut() {
-        if lvl.as_ref() != &DestructionLevel::Zero {
-            match bbd.0 {
-                Some(bbd) => {
-                    if parent.get() != bbd.0 {
-                        next_mat.0 = Some(base_mat.0.clone());
-                        *lvl = DestructionLevel::Zero;
-                    }
-                },
-                None => {
-                    next_mat.0 = Some(base_mat.0.clone());
-                    *lvl = DestructionLevel::Zero;
-                }
-            }
+    if !actions.just_pressed(Action::PickBloc) {
+        return;
+    }
+    if !game_mode.can_pick_blocs() {
+        return
+    }
+
+    let global_pos = head.single();
+    let selected_bloc = match targeted_bloc(global_pos, &game_state, &chunks, &chunks_query) {
+        None => return,
+        Some((bloc, _)) => bloc
+    };
+    let item = Item::Bloc(*blocs_types_query.get(selected_bloc).unwrap());
+
+    let mut inventory = inventory.single_mut();
+    // Select the item if it is already in the hotbar
+    if let Some(slot) = inventory.slots[..HOTBAR_SIZE].iter().position(|stack| stack.map(|s| s.item) == Some(item)) {
+        inventory.select(slot);
+        return
+    }
+    let selected = inventory.selected;
+    if let Some(slot) = inventory.slots.get_mut(selected) {
+        *slot = Some(ItemStack { item, count: 1 });
+    }
+}
+
+pub fn show_cracks(
+    bbd: Query<&BlocBeingDestroyed, (With<HeadMarker>, Changed<BlocBeingDestroyed>)>,
+    blocs: Query<&GlobalTransform, With<BlocType>>,
+    mut overlay: Query<(&mut Transform, &mut Visibility), With<CrackOverlay>>,
+    cracks: Res<Cracks>,
+    mut materials: ResMut<Assets<CrackMaterial>>
+) {
+    let bbd = match bbd.get_single() {
+        Ok(bbd) => bbd,
+        Err(_) => return
+    };
+    let (mut pos, mut visibility) = overlay.single_mut();
+    let (bloc, progress) = match bbd.0 {
+        Some(bbd) => bbd,
+        None => {
+            *visibility = Visibility::Hidden;
+            return
+        }
+    };
+    let bloc_pos = match blocs.get(bloc) {
+        Ok(bloc_pos) => bloc_pos,
+        Err(_) => {
+            *visibility = Visibility::Hidden;
+            return
         }
+    };
+    pos.translation = bloc_pos.translation();
+    *visibility = Visibility::Inherited;
+
+    // Only touch the material when the stage changes, so it isn't uploaded again every frame
+    let stage = ((progress * CRACK_STAGES as f32) as u32).min(CRACK_STAGES as u32 - 1);
+    if materials.get(&cracks.material).is_some_and(|material| material.stage.x != stage) {
+        materials.get_mut(&cracks.material).unwrap().stage.x = stage;
     }
-}
\ No newline at end of file
+}
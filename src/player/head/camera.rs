@@ -1,5 +1,8 @@
 use bevy::{prelude::*, input::mouse::MouseMotion, window::{PrimaryWindow, CursorGrabMode}};
-use crate::{PlayerMarker, HeadMarker};
+use crate::{Action, Actions, PlayerMarker, HeadMarker};
+
+/// Camera rotation speed with a gamepad stick fully tilted, in mouse pixels per second
+const GAMEPAD_LOOK_SPEED: f32 = 600.0;
 
 pub struct CameraPlugin;
 impl Plugin for CameraPlugin {
@@ -8,6 +11,7 @@ impl Plugin for CameraPlugin {
 
         #[cfg(target_arch = "wasm32")]
         app.add_systems(Update, cursor_grab)
+            .add_systems(PreUpdate, update_mouse_buttons.after(InputSystem))
             .insert_resource(WasmMouseTracker::new());
 
         #[cfg(not(target_arch = "wasm32"))]
@@ -38,11 +42,19 @@ pub fn rotate_camera_from_vec2(mov: Vec2, player_pos: &mut Mut<Transform>, cam_p
     cam_pos.rotation = Quat::from_axis_angle(Vec3::X, config.pitch);
 }
 
+/// Rotation from the gamepad right stick, in the same unit as a mouse movement
+fn gamepad_look(actions: &Actions, time: &Time) -> Vec2 {
+    let look = actions.look();
+    Vec2::new(look.x, -look.y) * GAMEPAD_LOOK_SPEED * time.delta_seconds()
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub fn rotate_camera(
     mut motion_evr: EventReader<MouseMotion>,
     mut cam: Query<(&mut CameraConfig, &mut Transform), (With<HeadMarker>, Without<PlayerMarker>)>,
-    mut player_pos: Query<&mut Transform, (With<PlayerMarker>, Without<HeadMarker>)>
+    mut player_pos: Query<&mut Transform, (With<PlayerMarker>, Without<HeadMarker>)>,
+    actions: Actions,
+    time: Res<Time>
 ) {
     let (mut config, mut cam_pos) = cam.single_mut();
     let mut player_pos = player_pos.single_mut();
@@ -50,6 +62,10 @@ pub fn rotate_camera(
         let mov = ev.delta;
         rotate_camera_from_vec2(mov, &mut player_pos, &mut cam_pos, &mut config);
     }
+    let mov = gamepad_look(&actions, &time);
+    if mov != Vec2::ZERO {
+        rotate_camera_from_vec2(mov, &mut player_pos, &mut cam_pos, &mut config);
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -69,9 +85,9 @@ pub fn cursor_grab(
 #[cfg(not(target_arch = "wasm32"))]
 pub fn cursor_release(
     mut q_windows: Query<&mut Window, With<PrimaryWindow>>,
-    keys: Res<ButtonInput<KeyCode>>
+    actions: Actions
 ) {
-    if !keys.just_pressed(KeyCode::Escape) {
+    if !actions.just_pressed(Action::ReleaseCursor) {
         return
     }
     let mut primary_window = q_windows.single_mut();
@@ -92,6 +108,8 @@ pub fn cursor_release(
 use std::sync::{Arc, atomic::{AtomicI32, AtomicBool, Ordering::SeqCst}};
 #[cfg(target_arch = "wasm32")]
 use web_sys::wasm_bindgen::JsCast;
+#[cfg(target_arch = "wasm32")]
+use bevy::input::InputSystem;
 
 #[cfg(target_arch = "wasm32")]
 fn get_body() -> web_sys::HtmlElement {
@@ -103,7 +121,8 @@ fn get_body() -> web_sys::HtmlElement {
 pub struct WasmMouseTracker {
     delta_x: Arc<AtomicI32>,
     delta_y: Arc<AtomicI32>,
-    mouse_down: Arc<AtomicBool>
+    /// Left, middle and right buttons
+    buttons: Arc<[AtomicBool; 3]>
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -123,21 +142,27 @@ impl WasmMouseTracker {
         });
         on_move.forget();
 
-        let mouse_down = Arc::new(AtomicBool::new(false));
+        let buttons = Arc::new([AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false)]);
 
-        let md = Arc::clone(&mouse_down);
-        let on_mouse_down = gloo::events::EventListener::new(&get_body(), "mousedown", move |_| {
-            md.store(true, SeqCst);
+        let b = Arc::clone(&buttons);
+        let on_mouse_down = gloo::events::EventListener::new(&get_body(), "mousedown", move |e| {
+            let mouse_event = e.clone().dyn_into::<web_sys::MouseEvent>().unwrap();
+            if let Some(button) = b.get(mouse_event.button() as usize) {
+                button.store(true, SeqCst);
+            }
         });
         on_mouse_down.forget();
 
-        let md = Arc::clone(&mouse_down);
-        let on_mouse_up = gloo::events::EventListener::new(&get_body(), "mouseup", move |_| {
-            md.store(false, SeqCst);
+        let b = Arc::clone(&buttons);
+        let on_mouse_up = gloo::events::EventListener::new(&get_body(), "mouseup", move |e| {
+            let mouse_event = e.clone().dyn_into::<web_sys::MouseEvent>().unwrap();
+            if let Some(button) = b.get(mouse_event.button() as usize) {
+                button.store(false, SeqCst);
+            }
         });
         on_mouse_up.forget();
 
-        Self { delta_x, delta_y, mouse_down }
+        Self { delta_x, delta_y, buttons }
     }
 
     pub fn get_delta_and_reset(&self) -> Vec2 {
@@ -149,8 +174,31 @@ impl WasmMouseTracker {
         self.delta_y.store(0, SeqCst);
         delta
     }
-    pub fn is_mouse_down(&self) -> bool {
-        self.mouse_down.load(SeqCst)
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        let i = match button {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            _ => return false
+        };
+        self.buttons[i].load(SeqCst)
+    }
+}
+
+/// Feed the tracked buttons to `ButtonInput<MouseButton>` so that actions work the same way as on desktop
+#[cfg(target_arch = "wasm32")]
+pub fn update_mouse_buttons(
+    wasm_mouse_tracker: Res<WasmMouseTracker>,
+    mut mouse: ResMut<ButtonInput<MouseButton>>
+) {
+    for button in [MouseButton::Left, MouseButton::Middle, MouseButton::Right] {
+        if wasm_mouse_tracker.is_pressed(button) {
+            if !mouse.pressed(button) {
+                mouse.press(button);
+            }
+        } else if mouse.pressed(button) {
+            mouse.release(button);
+        }
     }
 }
 
@@ -165,11 +213,13 @@ pub fn cursor_grab() {
 pub fn rotate_camera(
     wasm_mouse_tracker: Res<WasmMouseTracker>,
     mut cam: Query<(&mut CameraConfig, &mut Transform), (With<HeadMarker>, Without<PlayerMarker>)>,
-    mut player_pos: Query<&mut Transform, (With<PlayerMarker>, Without<HeadMarker>)>
+    mut player_pos: Query<&mut Transform, (With<PlayerMarker>, Without<HeadMarker>)>,
+    actions: Actions,
+    time: Res<Time>
 ) {
     let (mut config, mut cam_pos) = cam.single_mut();
     let mut player_pos = player_pos.single_mut();
-    let mov = wasm_mouse_tracker.get_delta_and_reset();
+    let mov = wasm_mouse_tracker.get_delta_and_reset() + gamepad_look(&actions, &time);
     if mov != Vec2::ZERO {
         rotate_camera_from_vec2(mov, &mut player_pos, &mut cam_pos, &mut config)
     }
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use crate::{targeted_bloc, ChunkBlocs, Chunks, DefaultGenerator, GameState, HeadMarker, SQUARE_UNIT};
+
+// Slightly bigger than the bloc so it isn't hidden by its faces
+const OUTLINE_SCALE: f32 = 1.005;
+const OUTLINE_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.8);
+const FACE_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.8);
+const CROSSHAIR_SIZE: f32 = 16.0;
+const CROSSHAIR_THICKNESS: f32 = 2.0;
+
+pub struct TargetPlugin;
+impl Plugin for TargetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_crosshair)
+            .add_systems(Update, outline_targeted_bloc);
+    }
+}
+
+pub fn outline_targeted_bloc(
+    head: Query<&GlobalTransform, With<HeadMarker>>,
+    game_state: Res<GameState>,
+    chunks: Res<Chunks<DefaultGenerator>>,
+    chunks_query: Query<&ChunkBlocs>,
+    mut gizmos: Gizmos
+) {
+    let head = match head.get_single() {
+        Ok(head) => head,
+        Err(_) => return
+    };
+    let hit = match targeted_bloc(head, &game_state, &chunks, &chunks_query) {
+        None => return,
+        Some((_, hit)) => hit
+    };
+    let center = hit.translation();
+    gizmos.cuboid(
+        Transform::from_translation(center).with_scale(Vec3::splat(SQUARE_UNIT*OUTLINE_SCALE)),
+        OUTLINE_COLOR
+    );
+
+    // Smaller square on the face, so it doesn't overlap the bloc outline
+    let normal = hit.face.normal();
+    gizmos.rect(
+        center + normal*(SQUARE_UNIT*OUTLINE_SCALE/2.0),
+        Quat::from_rotation_arc(Vec3::Z, normal),
+        Vec2::splat(SQUARE_UNIT*0.9),
+        FACE_COLOR
+    );
+}
+
+pub fn setup_crosshair(
+    mut cmds: Commands
+) {
+    cmds.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|screen| {
+        screen.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(CROSSHAIR_SIZE),
+                height: Val::Px(CROSSHAIR_SIZE),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        }).with_children(|crosshair| {
+            // Horizontal and vertical bars
+            for (width, height) in [(CROSSHAIR_SIZE, CROSSHAIR_THICKNESS), (CROSSHAIR_THICKNESS, CROSSHAIR_SIZE)] {
+                crosshair.spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Px(width),
+                        height: Val::Px(height),
+                        ..default()
+                    },
+                    background_color: Color::rgba(1.0, 1.0, 1.0, 0.8).into(),
+                    ..default()
+                });
+            }
+        });
+    });
+}
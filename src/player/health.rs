@@ -0,0 +1,238 @@
+use bevy::prelude::*;
+use crate::{Action, Actions, DropItem, Frozen, GameMode, Inventory, PlayerMarker, PlayerVelocity, GRAVITY, CHUNK_Y, SQUARE_UNIT};
+
+pub const MAX_HEALTH: f32 = 20.0;
+/// Falls lower than this (in blocs) don't hurt
+const SAFE_FALL_HEIGHT: f32 = 3.0;
+/// Damage per bloc fallen above `SAFE_FALL_HEIGHT`
+const FALL_DAMAGE_PER_BLOC: f32 = 1.0;
+
+pub struct HealthPlugin;
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldSpawn>()
+            .add_event::<PlayerLanded>()
+            .add_systems(Startup, setup_health_ui)
+            .add_systems(Startup, setup_death_screen)
+            .add_systems(Update, fall_damage)
+            .add_systems(Update, die.after(fall_damage))
+            .add_systems(Update, set_spawn_point)
+            .add_systems(Update, respawn.after(die))
+            .add_systems(Update, update_health_ui.after(respawn))
+            .add_systems(Update, update_death_screen.after(respawn));
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Health(pub f32);
+impl Default for Health {
+    fn default() -> Self {
+        Self(MAX_HEALTH)
+    }
+}
+impl Health {
+    pub fn is_dead(&self) -> bool {
+        self.0 <= 0.0
+    }
+}
+
+/// Run condition of the systems the player can't use while dead
+pub fn player_is_alive(health: Query<&Health, With<PlayerMarker>>) -> bool {
+    health.get_single().is_ok_and(|health| !health.is_dead())
+}
+
+/// Where players without their own spawn point respawn
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct WorldSpawn(pub Vec3);
+impl Default for WorldSpawn {
+    fn default() -> Self {
+        Self(Vec3::new(0.0, (CHUNK_Y as f32)*SQUARE_UNIT, 0.0))
+    }
+}
+
+/// Where the player respawns, the world spawn is used if it is `None`
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct SpawnPoint(pub Option<Vec3>);
+impl SpawnPoint {
+    pub fn get(&self, world_spawn: &WorldSpawn) -> Vec3 {
+        self.0.unwrap_or(world_spawn.0)
+    }
+}
+
+/// Sent when the player touches the ground after falling
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlayerLanded {
+    /// Vertical speed just before landing, in blocs per second
+    pub speed: f32
+}
+
+pub fn fall_damage(
+    mut ev_landed: EventReader<PlayerLanded>,
+    mut health: Query<&mut Health, With<PlayerMarker>>,
+    game_mode: Res<GameMode>
+) {
+    let mut health = health.single_mut();
+    for ev in ev_landed.read() {
+        if !game_mode.takes_damage() || health.is_dead() {
+            continue
+        }
+        let height = (ev.speed*ev.speed) / (2.0*GRAVITY);
+        let damage = ((height - SAFE_FALL_HEIGHT)*FALL_DAMAGE_PER_BLOC).ceil();
+        if damage > 0.0 {
+            health.0 = (health.0 - damage).max(0.0);
+        }
+    }
+}
+
+/// Drop the inventory when the player dies
+#[allow(clippy::type_complexity)]
+pub fn die(
+    mut player: Query<(&Health, &mut Inventory, &Transform), (With<PlayerMarker>, Changed<Health>)>,
+    mut ev_drop: EventWriter<DropItem>
+) {
+    let (health, mut inventory, pos) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return
+    };
+    if !health.is_dead() {
+        return
+    }
+    for slot in inventory.slots.iter_mut() {
+        if let Some(stack) = slot.take() {
+            ev_drop.send(DropItem {
+                stack,
+                pos: pos.translation,
+                age: 0.0
+            });
+        }
+    }
+}
+
+pub fn set_spawn_point(
+    mut player: Query<(&Health, &Transform, &mut SpawnPoint), With<PlayerMarker>>,
+    actions: Actions
+) {
+    if !actions.just_pressed(Action::SetSpawnPoint) {
+        return
+    }
+    let (health, pos, mut spawn_point) = player.single_mut();
+    if health.is_dead() {
+        return
+    }
+    spawn_point.0 = Some(pos.translation);
+    info!("Spawn point set to {}", pos.translation);
+}
+
+pub fn respawn(
+    mut player: Query<(&mut Health, &mut Transform, &mut PlayerVelocity, &SpawnPoint, Entity), With<PlayerMarker>>,
+    world_spawn: Res<WorldSpawn>,
+    actions: Actions,
+    mut cmds: Commands
+) {
+    let (mut health, mut pos, mut vel, spawn_point, player) = player.single_mut();
+    if !health.is_dead() || !actions.just_pressed(Action::Jump) {
+        return
+    }
+    health.0 = MAX_HEALTH;
+    pos.translation = spawn_point.get(&world_spawn);
+    vel.0 = Vec3::ZERO;
+    cmds.entity(player).insert(Frozen);
+}
+
+#[derive(Component)]
+pub struct HealthBarFill;
+
+pub fn setup_health_ui(
+    mut cmds: Commands
+) {
+    cmds.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(82.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(200.0),
+                height: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+            ..default()
+        }).with_children(|bar| {
+            bar.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.8, 0.1, 0.1).into(),
+                ..default()
+            }, HealthBarFill));
+        });
+    });
+}
+
+pub fn update_health_ui(
+    health: Query<&Health, (With<PlayerMarker>, Changed<Health>)>,
+    mut fill: Query<&mut Style, With<HealthBarFill>>
+) {
+    let health = match health.get_single() {
+        Ok(health) => health,
+        Err(_) => return
+    };
+    fill.single_mut().width = Val::Percent(100.0*health.0/MAX_HEALTH);
+}
+
+#[derive(Component)]
+pub struct DeathScreen;
+
+pub fn setup_death_screen(
+    mut cmds: Commands
+) {
+    cmds.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::rgba(0.5, 0.0, 0.0, 0.5).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(10),
+        ..default()
+    }, DeathScreen)).with_children(|screen| {
+        screen.spawn(TextBundle::from_section("You died!", TextStyle {
+            font_size: 64.0,
+            color: Color::WHITE,
+            ..default()
+        }));
+        screen.spawn(TextBundle::from_section("Press jump to respawn", TextStyle {
+            font_size: 24.0,
+            color: Color::WHITE,
+            ..default()
+        }));
+    });
+}
+
+pub fn update_death_screen(
+    health: Query<&Health, (With<PlayerMarker>, Changed<Health>)>,
+    mut screen: Query<&mut Visibility, With<DeathScreen>>
+) {
+    let health = match health.get_single() {
+        Ok(health) => health,
+        Err(_) => return
+    };
+    *screen.single_mut() = if health.is_dead() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+}
@@ -0,0 +1,293 @@
+use bevy::{prelude::*, input::mouse::MouseWheel};
+use serde::{Serialize, Deserialize};
+use crate::{Action, Actions, BlocType, PlayerMarker};
+
+pub const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 36;
+pub const MAX_STACK_SIZE: u32 = 64;
+
+pub struct InventoryPlugin;
+impl Plugin for InventoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_hotbar_ui)
+            .add_systems(Update, select_hotbar_slot)
+            .add_systems(Update, update_hotbar_ui);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Item {
+    Bloc(BlocType)
+}
+impl Item {
+    /// Item dropped when a bloc is broken
+    pub fn from_broken_bloc(bloc: BlocType) -> Option<Self> {
+        match bloc {
+            BlocType::Air => None,
+            BlocType::Grass | BlocType::SnowyDirt => Some(Self::Bloc(BlocType::Dirt)),
+            bloc => Some(Self::Bloc(bloc))
+        }
+    }
+    /// Bloc placed when using this item
+    pub fn bloc(&self) -> Option<BlocType> {
+        match self {
+            Self::Bloc(bloc) => Some(*bloc)
+        }
+    }
+    pub fn max_stack_size(&self) -> u32 {
+        MAX_STACK_SIZE
+    }
+    pub fn icon_path(&self) -> String {
+        match self {
+            Self::Bloc(bloc) => format!("{}/front.png", bloc)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: Item,
+    pub count: u32
+}
+
+/// Items of the player. The first `HOTBAR_SIZE` slots are the hotbar
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Inventory {
+    pub slots: Vec<Option<ItemStack>>,
+    /// Selected hotbar slot
+    pub selected: usize
+}
+impl Default for Inventory {
+    fn default() -> Self {
+        Self {
+            slots: vec![None; INVENTORY_SIZE],
+            selected: 0
+        }
+    }
+}
+impl Inventory {
+    /// Add items, filling the existing stacks first. Returns the number of items that didn't fit
+    pub fn add(&mut self, item: Item, mut count: u32) -> u32 {
+        let max = item.max_stack_size();
+        for stack in self.slots.iter_mut().flatten() {
+            if count == 0 {
+                return 0
+            }
+            if stack.item == item && stack.count < max {
+                let n = (max - stack.count).min(count);
+                stack.count += n;
+                count -= n;
+            }
+        }
+        for slot in self.slots.iter_mut() {
+            if count == 0 {
+                return 0
+            }
+            if slot.is_none() {
+                let n = max.min(count);
+                *slot = Some(ItemStack { item, count: n });
+                count -= n;
+            }
+        }
+        count
+    }
+    pub fn selected_stack(&self) -> Option<&ItemStack> {
+        self.slots.get(self.selected)?.as_ref()
+    }
+    /// Remove one item from the selected slot and return it
+    pub fn take_selected(&mut self) -> Option<Item> {
+        let slot = self.slots.get_mut(self.selected)?;
+        let stack = slot.as_mut()?;
+        let item = stack.item;
+        stack.count -= 1;
+        if stack.count == 0 {
+            *slot = None;
+        }
+        Some(item)
+    }
+    pub fn select(&mut self, slot: usize) {
+        self.selected = slot % HOTBAR_SIZE;
+    }
+    /// Move the selection by `delta` slots, wrapping around the hotbar
+    pub fn scroll(&mut self, delta: i32) {
+        self.selected = (self.selected as i32 + delta).rem_euclid(HOTBAR_SIZE as i32) as usize;
+    }
+    /// Fix an inventory read from a save: `INVENTORY_SIZE` slots, no empty or overfull stack and a hotbar slot selected
+    pub fn repair(&mut self) {
+        self.slots.resize(INVENTORY_SIZE, None);
+        for slot in self.slots.iter_mut() {
+            match slot {
+                Some(stack) if stack.count == 0 => *slot = None,
+                Some(stack) => stack.count = stack.count.min(stack.item.max_stack_size()),
+                None => {}
+            }
+        }
+        self.select(self.selected);
+    }
+}
+
+pub fn select_hotbar_slot(
+    actions: Actions,
+    mut wheel_evr: EventReader<MouseWheel>,
+    mut inventory: Query<&mut Inventory, With<PlayerMarker>>
+) {
+    let mut inventory = inventory.single_mut();
+    for i in 0..HOTBAR_SIZE {
+        if actions.just_pressed(Action::Hotbar(i as u8)) {
+            inventory.select(i);
+        }
+    }
+    for ev in wheel_evr.read() {
+        if ev.y > 0.0 {
+            inventory.scroll(-1);
+        } else if ev.y < 0.0 {
+            inventory.scroll(1);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct HotbarSlot(usize);
+
+#[derive(Component)]
+pub struct HotbarSlotIcon(usize);
+
+#[derive(Component)]
+pub struct HotbarSlotCount(usize);
+
+pub fn setup_hotbar_ui(
+    mut cmds: Commands
+) {
+    cmds.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|hotbar| {
+        for i in 0..HOTBAR_SIZE {
+            hotbar.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Px(50.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::all(Val::Px(2.0)),
+                    border: UiRect::all(Val::Px(3.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+                border_color: Color::GRAY.into(),
+                ..default()
+            }, HotbarSlot(i))).with_children(|slot| {
+                slot.spawn((ImageBundle {
+                    style: Style {
+                        width: Val::Px(36.0),
+                        height: Val::Px(36.0),
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                }, HotbarSlotIcon(i)));
+                slot.spawn((TextBundle::from_section("", TextStyle {
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                    ..default()
+                }).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(2.0),
+                    bottom: Val::Px(0.0),
+                    ..default()
+                }), HotbarSlotCount(i)));
+            });
+        }
+    });
+}
+
+pub fn update_hotbar_ui(
+    inventory: Query<&Inventory, (With<PlayerMarker>, Changed<Inventory>)>,
+    mut slots: Query<(&HotbarSlot, &mut BorderColor)>,
+    mut icons: Query<(&HotbarSlotIcon, &mut UiImage, &mut Visibility)>,
+    mut counts: Query<(&HotbarSlotCount, &mut Text)>,
+    asset_server: Res<AssetServer>
+) {
+    let inventory = match inventory.get_single() {
+        Ok(inventory) => inventory,
+        Err(_) => return
+    };
+    for (slot, mut border) in slots.iter_mut() {
+        border.0 = if slot.0 == inventory.selected {
+            Color::WHITE
+        } else {
+            Color::GRAY
+        };
+    }
+    for (icon, mut image, mut visibility) in icons.iter_mut() {
+        match &inventory.slots[icon.0] {
+            Some(stack) => {
+                image.texture = asset_server.load(stack.item.icon_path());
+                *visibility = Visibility::Inherited;
+            },
+            None => *visibility = Visibility::Hidden
+        }
+    }
+    for (count, mut text) in counts.iter_mut() {
+        text.sections[0].value = match &inventory.slots[count.0] {
+            Some(stack) if stack.count > 1 => stack.count.to_string(),
+            _ => String::new()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRT: Item = Item::Bloc(BlocType::Dirt);
+    const STONE: Item = Item::Bloc(BlocType::Stone);
+
+    #[test]
+    fn add_fills_existing_stacks_first() {
+        let mut inventory = Inventory::default();
+        inventory.slots[3] = Some(ItemStack { item: DIRT, count: 60 });
+        assert_eq!(inventory.add(DIRT, 10), 0);
+        assert_eq!(inventory.slots[3], Some(ItemStack { item: DIRT, count: MAX_STACK_SIZE }));
+        assert_eq!(inventory.slots[0], Some(ItemStack { item: DIRT, count: 6 }));
+    }
+
+    #[test]
+    fn add_returns_the_leftovers() {
+        let mut inventory = Inventory::default();
+        for slot in inventory.slots.iter_mut().skip(1) {
+            *slot = Some(ItemStack { item: STONE, count: 1 });
+        }
+        assert_eq!(inventory.add(DIRT, MAX_STACK_SIZE + 5), 5);
+        assert_eq!(inventory.slots[0], Some(ItemStack { item: DIRT, count: MAX_STACK_SIZE }));
+        assert_eq!(inventory.add(DIRT, 3), 3);
+    }
+
+    #[test]
+    fn repair_fixes_a_corrupt_inventory() {
+        let mut inventory = Inventory {
+            slots: vec![Some(ItemStack { item: DIRT, count: 0 }), Some(ItemStack { item: STONE, count: 500 })],
+            selected: 50
+        };
+        inventory.repair();
+        assert_eq!(inventory.slots.len(), INVENTORY_SIZE);
+        assert!(inventory.selected < HOTBAR_SIZE);
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.slots[1], Some(ItemStack { item: STONE, count: MAX_STACK_SIZE }));
+        inventory.select(0);
+        assert_eq!(inventory.take_selected(), None);
+
+        let mut inventory = Inventory {
+            slots: Vec::new(),
+            selected: 50
+        };
+        assert_eq!(inventory.selected_stack(), None);
+        assert_eq!(inventory.take_selected(), None);
+    }
+}
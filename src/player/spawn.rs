@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::{Chunks, ChunkPhysic, ChunkPos, ChunkSaves, DefaultGenerator, Flight, FlightState, GameState, Generator, PlayerMarker, PlayerVelocity, BLOCS_PHYSIC_GROUP, CHUNK_Y, PHYSIC_DISTANCE, PLAYER_HITBOX_HEIGHT, PLAYER_PHYSIC_GROUP, SQUARE_UNIT};
+
+/// Max distance (in blocs) from the searched position where a spawn point can be
+const SPAWN_SEARCH_RADIUS: i32 = 64;
+/// Max height difference (in blocs) between the spawn column and its neighbors
+const MAX_SPAWN_SLOPE: u32 = 1;
+/// Depth (in blocs) under the bottom of the world where the player is brought back to the surface
+const OUT_OF_WORLD_DEPTH: f32 = 32.0;
+
+pub struct SpawnPlugin;
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, freeze_player)
+            .add_systems(Update, release_player.after(freeze_player))
+            .add_systems(Update, rescue_player_out_of_world.before(freeze_player));
+    }
+}
+
+/// The player can't move until the world around it is generated and has colliders
+#[derive(Component)]
+pub struct Frozen;
+
+/// Position of the player standing on the surface at these bloc coordinates
+pub fn surface_position(x: i32, z: i32, height: u32) -> Vec3 {
+    Vec3::new(
+        x as f32*SQUARE_UNIT,
+        (height as f32 - 0.5)*SQUARE_UNIT + (PLAYER_HITBOX_HEIGHT/2.0) + 0.1,
+        z as f32*SQUARE_UNIT
+    )
+}
+
+/// Surface height of this column if the player can safely stand on it
+fn is_safe_spawn<G: Generator>(generator: &G, x: i32, z: i32) -> Option<u32> {
+    let height = generator.surface_height(x, z)?;
+    // Room for the player above the surface
+    if height as f32 + PLAYER_HITBOX_HEIGHT/SQUARE_UNIT > CHUNK_Y as f32 {
+        return None
+    }
+    // Avoid holes and cliffs
+    for (dx, dz) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let neighbor = generator.surface_height(x+dx, z+dz)?;
+        if neighbor.abs_diff(height) > MAX_SPAWN_SLOPE {
+            return None
+        }
+    }
+    Some(height)
+}
+
+/// Search the closest safe position to stand on around the bloc coordinates `around`
+pub fn find_spawn<G: Generator>(generator: &G, around: IVec2) -> Vec3 {
+    for r in 0..=SPAWN_SEARCH_RADIUS {
+        for dx in -r..=r {
+            for dz in -r..=r {
+                // Only check the ring at distance `r`, the inner ones have already been checked
+                if dx.abs() != r && dz.abs() != r {
+                    continue
+                }
+                let (x, z) = (around.x + dx, around.y + dz);
+                if let Some(height) = is_safe_spawn(generator, x, z) {
+                    return surface_position(x, z, height)
+                }
+            }
+        }
+    }
+    warn!("No safe spawn found around {:?}", around);
+    surface_position(around.x, around.y, generator.surface_height(around.x, around.y).unwrap_or(CHUNK_Y as u32))
+}
+
+/// Chunks that must be loaded before releasing a player at this position
+pub fn chunks_around(pos: Vec3) -> Vec<ChunkPos> {
+    let center = ChunkPos::from_translation(pos);
+    let d = PHYSIC_DISTANCE as i32;
+    let mut chunks = Vec::new();
+    for x in -d..=d {
+        for z in -d..=d {
+            chunks.push(ChunkPos { x: center.x + x, y: 0, z: center.z + z });
+        }
+    }
+    chunks
+}
+
+/// True if there are bloc colliders under this position
+fn has_ground_colliders(rapier_ctx: &RapierContext, pos: Vec3) -> bool {
+    rapier_ctx.cast_ray(
+        pos,
+        -Vec3::Y,
+        pos.y.max(0.0) + SQUARE_UNIT,
+        true,
+        QueryFilter::default().groups(CollisionGroups::new(PLAYER_PHYSIC_GROUP, BLOCS_PHYSIC_GROUP))
+    ).is_some()
+}
+
+/// True if the player can't fall through missing colliders at this position: there is ground under it or the physic of its chunk is loaded
+fn is_physic_ready(pos: Vec3, rapier_ctx: &RapierContext, chunks: &Chunks<DefaultGenerator>, physics: &Query<&ChunkPhysic>) -> bool {
+    if has_ground_colliders(rapier_ctx, pos) {
+        return true
+    }
+    let chunk = ChunkPos { y: 0, ..ChunkPos::from_translation(pos) };
+    match chunks.get(chunk) {
+        None => false,
+        Some(chunk) => physics.get(*chunk).is_ok_and(|physic| *physic == ChunkPhysic::Loaded)
+    }
+}
+
+/// Freeze the walking player when the colliders under it are not loaded yet, so it can't fall through the world. The chunk of the player is loaded right away if needed
+#[allow(clippy::type_complexity)]
+pub fn freeze_player(
+    player: Query<(Entity, &Transform, &Flight), (With<PlayerMarker>, Without<Frozen>)>,
+    mut chunks: ResMut<Chunks<DefaultGenerator>>,
+    chunk_saves: Res<ChunkSaves>,
+    mut game_state: ResMut<GameState>,
+    rapier_ctx: Res<RapierContext>,
+    physics: Query<&ChunkPhysic>,
+    mut cmds: Commands
+) {
+    let (player, pos, flight) = match player.get_single() {
+        Ok(player) => player,
+        Err(_) => return
+    };
+    if flight.is_flying() || is_physic_ready(pos.translation, &rapier_ctx, &chunks, &physics) {
+        return
+    }
+    let chunk = ChunkPos::from_translation(pos.translation);
+    chunks.load_or_generate(ChunkPos { y: 0, ..chunk }, &chunk_saves, &mut game_state, &mut cmds);
+    cmds.entity(player).insert(Frozen);
+}
+
+/// Bring the player back to the surface if it fell under the world
+pub fn rescue_player_out_of_world(
+    mut player: Query<(Entity, &mut Transform, &mut PlayerVelocity, &Flight), With<PlayerMarker>>,
+    chunks: Res<Chunks<DefaultGenerator>>,
+    mut cmds: Commands
+) {
+    let (player, mut pos, mut vel, flight) = player.single_mut();
+    if pos.translation.y > -OUT_OF_WORLD_DEPTH*SQUARE_UNIT || flight.state == FlightState::Noclip {
+        return
+    }
+    let around = IVec2::new((pos.translation.x/SQUARE_UNIT).round() as i32, (pos.translation.z/SQUARE_UNIT).round() as i32);
+    pos.translation = find_spawn(&chunks.generator, around);
+    vel.0 = Vec3::ZERO;
+    cmds.entity(player).insert(Frozen);
+    warn!("Player fell out of the world, moved back to {:?}", pos.translation);
+}
+
+/// Release the frozen player once the chunks around it exist and their physic is loaded
+#[allow(clippy::type_complexity)]
+pub fn release_player(
+    mut player: Query<(Entity, &Transform, &mut PlayerVelocity), (With<PlayerMarker>, With<Frozen>)>,
+    chunks: Res<Chunks<DefaultGenerator>>,
+    rapier_ctx: Res<RapierContext>,
+    physics: Query<&ChunkPhysic>,
+    mut cmds: Commands
+) {
+    let (player, pos, mut vel) = match player.get_single_mut() {
+        Ok(player) => player,
+        Err(_) => return
+    };
+    vel.0 = Vec3::ZERO;
+    if chunks_around(pos.translation).into_iter().any(|chunk| chunks.get(chunk).is_none()) {
+        return
+    }
+    if is_physic_ready(pos.translation, &rapier_ctx, &chunks, &physics) {
+        cmds.entity(player).remove::<Frozen>();
+    }
+}
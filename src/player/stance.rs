@@ -0,0 +1,227 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::{Action, Actions, Flight, GameMode, HeadMarker, PlayerMarker, BLOCS_PHYSIC_GROUP, PLAYER_HITBOX_HEIGHT, PLAYER_HITBOX_RADIUS, PLAYER_PHYSIC_GROUP};
+
+pub const SPRINT_MULTIPLIER: f32 = 2.0;
+pub const CROUCH_MULTIPLIER: f32 = 0.3;
+pub const CROUCH_HITBOX_HEIGHT: f32 = 1.5;
+/// Distance between the top of the hitbox and the eyes
+const EYE_OFFSET: f32 = 0.4;
+const FOV: f32 = std::f32::consts::FRAC_PI_4;
+/// Fov multiplier while sprinting
+const SPRINT_FOV_MULTIPLIER: f32 = 1.15;
+/// Speed of the fov and camera height transitions
+const TRANSITION_SPEED: f32 = 10.0;
+const MAX_STAMINA: f32 = 5.0;
+/// Stamina used per second of sprint
+const STAMINA_DRAIN: f32 = 1.0;
+/// Stamina recovered per second without sprinting
+const STAMINA_REGEN: f32 = 0.5;
+
+pub struct StancePlugin;
+impl Plugin for StancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_stamina_ui)
+            .add_systems(Update, update_stamina.after(update_stance))
+            .add_systems(Update, animate_head.after(update_stance))
+            .add_systems(Update, update_stamina_ui.after(update_stamina));
+    }
+}
+
+#[derive(Component, Default)]
+pub struct Stance {
+    pub sprinting: bool,
+    pub crouching: bool
+}
+impl Stance {
+    pub fn hitbox_height(&self) -> f32 {
+        if self.crouching {
+            CROUCH_HITBOX_HEIGHT
+        } else {
+            PLAYER_HITBOX_HEIGHT
+        }
+    }
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.crouching {
+            CROUCH_MULTIPLIER
+        } else if self.sprinting {
+            SPRINT_MULTIPLIER
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Time (in seconds) of sprint left. Only used in survival
+#[derive(Component)]
+pub struct Stamina(pub f32);
+impl Default for Stamina {
+    fn default() -> Self {
+        Self(MAX_STAMINA)
+    }
+}
+
+pub fn player_collider(height: f32) -> Collider {
+    Collider::round_cylinder((height/2.0)-0.1, PLAYER_HITBOX_RADIUS-0.1, 0.1)
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_stance(
+    mut player: Query<(&mut Stance, &Stamina, &Flight, &mut Collider, &mut Transform, Entity), With<PlayerMarker>>,
+    rapier_ctx: Res<RapierContext>,
+    actions: Actions,
+    game_mode: Res<GameMode>
+) {
+    let (mut stance, stamina, flight, mut collider, mut pos, player) = player.single_mut();
+
+    let mut crouching = actions.pressed(Action::Crouch) && !flight.is_flying();
+    if stance.crouching && !crouching {
+        // Stay crouched if there is no room to stand up
+        let standing_center = pos.translation + Vec3::Y * (PLAYER_HITBOX_HEIGHT - CROUCH_HITBOX_HEIGHT) / 2.0;
+        let obstacle = rapier_ctx.intersection_with_shape(
+            standing_center,
+            Quat::IDENTITY,
+            &player_collider(PLAYER_HITBOX_HEIGHT),
+            QueryFilter::default()
+                .groups(CollisionGroups::new(PLAYER_PHYSIC_GROUP, BLOCS_PHYSIC_GROUP))
+                .exclude_collider(player)
+        );
+        crouching = obstacle.is_some() && !flight.is_flying();
+    }
+
+    let stamina_left = !game_mode.uses_stamina() || stamina.0 > 0.0;
+    let sprinting = actions.pressed(Action::Run) && actions.movement().y > 0.0 && !crouching && stamina_left;
+    if stance.sprinting != sprinting {
+        stance.sprinting = sprinting;
+    }
+
+    if stance.crouching == crouching {
+        return
+    }
+    // Keep the feet at the same place when the hitbox changes
+    let offset = (PLAYER_HITBOX_HEIGHT - CROUCH_HITBOX_HEIGHT) / 2.0;
+    if crouching {
+        pos.translation.y -= offset;
+        *collider = player_collider(CROUCH_HITBOX_HEIGHT);
+    } else {
+        pos.translation.y += offset;
+        *collider = player_collider(PLAYER_HITBOX_HEIGHT);
+    }
+    stance.crouching = crouching;
+}
+
+pub fn update_stamina(
+    mut player: Query<(&Stance, &mut Stamina), With<PlayerMarker>>,
+    time: Res<Time>,
+    game_mode: Res<GameMode>
+) {
+    let (stance, mut stamina) = player.single_mut();
+    if !game_mode.uses_stamina() {
+        if stamina.0 != MAX_STAMINA {
+            stamina.0 = MAX_STAMINA;
+        }
+        return
+    }
+    if stance.sprinting {
+        stamina.0 = (stamina.0 - STAMINA_DRAIN*time.delta_seconds()).max(0.0);
+    } else if stamina.0 < MAX_STAMINA {
+        stamina.0 = (stamina.0 + STAMINA_REGEN*time.delta_seconds()).min(MAX_STAMINA);
+    }
+}
+
+/// Move the camera down when crouching and widen the fov when sprinting
+pub fn animate_head(
+    player: Query<&Stance, With<PlayerMarker>>,
+    mut head: Query<(&mut Transform, &mut Projection), With<HeadMarker>>,
+    time: Res<Time>
+) {
+    let stance = player.single();
+    let (mut head_pos, mut projection) = head.single_mut();
+    let t = (TRANSITION_SPEED*time.delta_seconds()).min(1.0);
+
+    let eye_height = (stance.hitbox_height()/2.0) - EYE_OFFSET;
+    if head_pos.translation.y != eye_height {
+        head_pos.translation.y = head_pos.translation.y.lerp(eye_height, t);
+        if (head_pos.translation.y - eye_height).abs() < 0.001 {
+            head_pos.translation.y = eye_height;
+        }
+    }
+
+    if let Projection::Perspective(perspective) = projection.as_ref() {
+        let fov = if stance.sprinting {
+            FOV*SPRINT_FOV_MULTIPLIER
+        } else {
+            FOV
+        };
+        if perspective.fov != fov {
+            let mut new_fov = perspective.fov.lerp(fov, t);
+            if (new_fov - fov).abs() < 0.001 {
+                new_fov = fov;
+            }
+            *projection = Projection::Perspective(PerspectiveProjection { fov: new_fov, ..perspective.clone() });
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StaminaBar;
+
+#[derive(Component)]
+pub struct StaminaBarFill;
+
+pub fn setup_stamina_ui(
+    mut cmds: Commands
+) {
+    cmds.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(72.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }).with_children(|parent| {
+        parent.spawn((NodeBundle {
+            style: Style {
+                width: Val::Px(200.0),
+                height: Val::Px(6.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.4).into(),
+            ..default()
+        }, StaminaBar)).with_children(|bar| {
+            bar.spawn((NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.9, 0.8, 0.2).into(),
+                ..default()
+            }, StaminaBarFill));
+        });
+    });
+}
+
+pub fn update_stamina_ui(
+    player: Query<&Stamina, (With<PlayerMarker>, Changed<Stamina>)>,
+    mut bar: Query<&mut Visibility, With<StaminaBar>>,
+    mut fill: Query<&mut Style, With<StaminaBarFill>>,
+    game_mode: Res<GameMode>
+) {
+    let mut visibility = bar.single_mut();
+    let visible = if game_mode.uses_stamina() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if *visibility != visible {
+        *visibility = visible;
+    }
+    let stamina = match player.get_single() {
+        Ok(stamina) => stamina,
+        Err(_) => return
+    };
+    fill.single_mut().width = Val::Percent(100.0*stamina.0/MAX_STAMINA);
+}
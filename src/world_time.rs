@@ -0,0 +1,194 @@
+use std::f32::consts::TAU;
+use bevy::prelude::*;
+use serde::{Serialize, Deserialize};
+use crate::{Action, Actions};
+
+// At normal speed
+pub const DAY_LENGTH: f64 = 1200.0;
+const START_TIME: f64 = 0.3;
+
+const SUN_ILLUMINANCE: f32 = 3000.0;
+const MOON_ILLUMINANCE: f32 = 250.0;
+const DAY_AMBIENT: f32 = 80.0;
+const NIGHT_AMBIENT: f32 = 10.0;
+const NOON_SUN_COLOR: Color = Color::rgb(1.0, 1.0, 0.75);
+const SUNSET_SUN_COLOR: Color = Color::rgb(1.0, 0.55, 0.3);
+const MOON_COLOR: Color = Color::rgb(0.6, 0.7, 1.0);
+const DAY_SKY: Color = Color::rgb(0.5, 0.75, 1.0);
+const SUNSET_SKY: Color = Color::rgb(0.9, 0.5, 0.35);
+const NIGHT_SKY: Color = Color::rgb(0.02, 0.02, 0.08);
+const TIME_SPEEDS: [f32; 3] = [1.0, 20.0, 0.0];
+// Sine of the sun angle above the horizon where the day is fully bright
+const FULL_DAY_ELEVATION: f32 = 0.25;
+
+pub struct WorldTimePlugin;
+impl Plugin for WorldTimePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldTime>()
+            .add_event::<SetTime>()
+            .add_systems(Startup, setup_sky)
+            .add_systems(Update, skip_time)
+            .add_systems(Update, change_time_speed)
+            .add_systems(Update, advance_time.after(skip_time).after(change_time_speed))
+            .add_systems(Update, update_sky.after(advance_time));
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WorldTime {
+    // The fractional part is the time of day
+    pub days: f64,
+    pub speed: f32
+}
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self {
+            days: START_TIME,
+            speed: 1.0
+        }
+    }
+}
+impl WorldTime {
+    // 0.0 is midnight, 0.25 sunrise, 0.5 noon and 0.75 sunset
+    pub fn time_of_day(&self) -> f32 {
+        self.days.fract() as f32
+    }
+    // Without going back to the previous day
+    pub fn set_time_of_day(&mut self, time: f32) {
+        let day = self.days.floor();
+        let time = time.rem_euclid(1.0) as f64;
+        self.days = if day + time < self.days { day + 1.0 + time } else { day + time };
+    }
+    pub fn is_night(&self) -> bool {
+        self.sun_elevation() < 0.0
+    }
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day() - 0.25)*TAU;
+        Vec3::new(angle.cos(), angle.sin(), 0.3).normalize()
+    }
+    // Negative at night
+    pub fn sun_elevation(&self) -> f32 {
+        self.sun_direction().y
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub enum SetTime {
+    // See `WorldTime::time_of_day`
+    TimeOfDay(f32),
+    Speed(f32)
+}
+
+#[derive(Component)]
+pub struct Sun;
+
+#[derive(Component)]
+pub struct Moon;
+
+pub fn setup_sky(
+    mut cmds: Commands
+) {
+    cmds.spawn((DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            //shadows_enabled: true,
+            illuminance: SUN_ILLUMINANCE,
+            color: NOON_SUN_COLOR,
+            ..default()
+        },
+        ..default()
+    }, Sun));
+    cmds.spawn((DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 0.0,
+            color: MOON_COLOR,
+            ..default()
+        },
+        ..default()
+    }, Moon));
+}
+
+pub fn skip_time(
+    actions: Actions,
+    world_time: Res<WorldTime>,
+    mut ev_set_time: EventWriter<SetTime>
+) {
+    if !actions.just_pressed(Action::SkipTime) {
+        return
+    }
+    let next = ((world_time.time_of_day()*4.0).floor() + 1.0) / 4.0;
+    ev_set_time.send(SetTime::TimeOfDay(next));
+}
+
+pub fn change_time_speed(
+    actions: Actions,
+    world_time: Res<WorldTime>,
+    mut ev_set_time: EventWriter<SetTime>
+) {
+    if !actions.just_pressed(Action::TimeSpeed) {
+        return
+    }
+    let next = match TIME_SPEEDS.iter().position(|speed| *speed == world_time.speed) {
+        Some(i) => TIME_SPEEDS[(i + 1) % TIME_SPEEDS.len()],
+        None => TIME_SPEEDS[0]
+    };
+    ev_set_time.send(SetTime::Speed(next));
+}
+
+pub fn advance_time(
+    mut world_time: ResMut<WorldTime>,
+    mut ev_set_time: EventReader<SetTime>,
+    time: Res<Time>
+) {
+    for ev in ev_set_time.read() {
+        match *ev {
+            SetTime::TimeOfDay(t) => world_time.set_time_of_day(t),
+            SetTime::Speed(speed) => world_time.speed = speed.max(0.0)
+        }
+        info!("Time : day {}, {:.2}, speed {}", world_time.days.floor(), world_time.time_of_day(), world_time.speed);
+    }
+    world_time.days += time.delta_seconds_f64() * world_time.speed as f64 / DAY_LENGTH;
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    Color::rgb(
+        a.r() + (b.r() - a.r())*t,
+        a.g() + (b.g() - a.g())*t,
+        a.b() + (b.b() - a.b())*t
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_sky(
+    world_time: Res<WorldTime>,
+    mut sun: Query<(&mut Transform, &mut DirectionalLight), (With<Sun>, Without<Moon>)>,
+    mut moon: Query<(&mut Transform, &mut DirectionalLight), (With<Moon>, Without<Sun>)>,
+    mut clear_color: ResMut<ClearColor>,
+    mut fogs: Query<&mut FogSettings>,
+    mut ambient: ResMut<AmbientLight>
+) {
+    let direction = world_time.sun_direction();
+    let elevation = world_time.sun_elevation();
+    // 0.0 at night, 1.0 in full day
+    let day = (elevation / FULL_DAY_ELEVATION).clamp(0.0, 1.0);
+    let night = (-elevation / FULL_DAY_ELEVATION).clamp(0.0, 1.0);
+
+    let (mut sun_pos, mut sun_light) = sun.single_mut();
+    *sun_pos = Transform::default().looking_to(-direction, Vec3::Y);
+    sun_light.illuminance = SUN_ILLUMINANCE * day;
+    sun_light.color = lerp_color(SUNSET_SUN_COLOR, NOON_SUN_COLOR, day);
+
+    let (mut moon_pos, mut moon_light) = moon.single_mut();
+    *moon_pos = Transform::default().looking_to(direction, Vec3::Y);
+    moon_light.illuminance = MOON_ILLUMINANCE * night;
+
+    // The sky is reddish when the sun is close to the horizon
+    let sunset = 1.0 - (elevation.abs() / FULL_DAY_ELEVATION).min(1.0);
+    let sky = if elevation >= 0.0 { DAY_SKY } else { NIGHT_SKY };
+    clear_color.0 = lerp_color(sky, SUNSET_SKY, sunset*0.7);
+    // The fog fades the far chunks into the sky
+    for mut fog in fogs.iter_mut() {
+        fog.color = clear_color.0;
+    }
+    ambient.brightness = NIGHT_AMBIENT + (DAY_AMBIENT - NIGHT_AMBIENT)*day;
+}